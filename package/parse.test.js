@@ -1,8 +1,11 @@
+const crypto = require('crypto')
 const dcc = require('dcc-wasm')
 
+const TEST_DCC = 'HC1:6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1'
+
 describe('It parses a valid dcc', () => {
 
-  let result = dcc.parse('HC1:6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1')
+  let result = dcc.parse(TEST_DCC)
 
   test('parse successful', () => {
     expect(result.successful).toBe(true)
@@ -16,10 +19,40 @@ describe('It parses a valid dcc', () => {
     expect(result.kid).toBe('DEsVUSvpFAE=')
   })
 
+  test('error code', () => {
+    expect(result.error_code).toBe(dcc.ErrorCode.UnknownKid)
+  })
+
+  test('verification error', () => {
+    expect(result.decode_error).toBeUndefined()
+    expect(result.verification_error.code).toBe(dcc.ErrorCode.UnknownKid)
+  })
+
   test('algorithm', () => {
     expect(result.algorithm).toBe(-7)
   })
 
+  test('claims', () => {
+    expect(result.claims.issuer).toBe('DE')
+    expect(result.claims.issued_at).toBe(1622316073)
+    expect(result.claims.expiration).toBe(1643356073)
+    expect(result.claims.hcert.ver).toBe('1.0.0')
+  })
+
+  test('expired', () => {
+    expect(result.validity).toBe(dcc.Validity.Expired)
+  })
+
+  test('valid at a moment in time', () => {
+    const options = new dcc.ParseOptions()
+    options.set_now(1630000000)
+    expect(dcc.parse_with_options(TEST_DCC, options).validity).toBe(dcc.Validity.Valid)
+  })
+
+  test('warnings', () => {
+    expect(result.warnings).toEqual([])
+  })
+
   test('data', () => {
     expect(result.data).toEqual({
       '1': 'DE',
@@ -52,6 +85,282 @@ describe('It parses a valid dcc', () => {
   })
 })
 
+describe('Typed health certificate', () => {
+
+  const result = dcc.parse(TEST_DCC)
+
+  test('recovery', () => {
+    expect(result.health_certificate.ver).toBe('1.0.0')
+    expect(result.health_certificate.nam.fnt).toBe('MUSTERMANN')
+    expect(result.health_certificate.r[0].ci).toBe('URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W')
+    expect(result.health_certificate.v).toBeUndefined()
+  })
+
+  test('schema version', () => {
+    expect(result.schema_version).toBe('1.0.0')
+    expect(result.schema_deviations).toEqual([])
+  })
+})
+
+describe('Certificate identifier', () => {
+
+  test('parsed from the dcc', () => {
+    const uvci = dcc.parse(TEST_DCC).uvci
+    expect(uvci.version).toBe('01')
+    expect(uvci.country).toBe('DE')
+    expect(uvci.issuing_entity).toBeUndefined()
+    expect(uvci.opaque_id).toBe('5CWLU12RNOB9RXSEOP6FG8')
+    expect(uvci.fragments).toEqual(['5CWLU12RNOB9RXSEOP6FG8'])
+    expect(uvci.checksum).toBe('W')
+  })
+
+  test('fragments', () => {
+    expect(dcc.parse_uvci('URN:UVCI:01:NL:A:B:C#X').fragments).toEqual(['A', 'B', 'C'])
+  })
+
+  test('checksum', () => {
+    expect(dcc.parse_uvci('URN:UVCI:01:SM:112#N').valid).toBe(true)
+    expect(dcc.parse_uvci('URN:UVCI:01:SM:113#N').valid).toBe(false)
+    expect(dcc.parse_uvci('URN:UVCI:01:NL:187/37512422923').valid).toBeUndefined()
+    expect(() => dcc.parse_uvci('URN:UVCI:01:NL')).toThrow()
+  })
+})
+
+describe('Schema validation', () => {
+
+  test('not validated by default', () => {
+    expect(dcc.parse(TEST_DCC).schema_violations).toEqual([])
+  })
+
+  test('conforms to the schema', () => {
+    const options = new dcc.ParseOptions()
+    options.set_validate_schema(true)
+    expect(dcc.parse_with_options(TEST_DCC, options).schema_violations).toEqual([])
+  })
+})
+
+describe('Value sets', () => {
+
+  test('display', () => {
+    const result = dcc.parse(TEST_DCC)
+    expect(result.display.r[0].tg).toBe('COVID-19')
+    expect(result.health_certificate.r[0].tg).toBe('840539006')
+  })
+
+  test('custom value set', () => {
+    const valueSets = new dcc.ValueSets()
+    valueSets.add_value_set(JSON.stringify({
+      valueSetId: 'disease-agent-targeted',
+      valueSetDate: '2021-04-27',
+      valueSetValues: { '840539006': { display: 'Corona' } }
+    }))
+
+    const options = new dcc.ParseOptions()
+    options.set_value_sets(valueSets)
+    expect(dcc.parse_with_options(TEST_DCC, options).display.r[0].tg).toBe('Corona')
+  })
+})
+
+describe('Business rules', () => {
+
+  const rules = JSON.stringify([{
+    Identifier: 'RR-NL-0001',
+    Type: 'Acceptance',
+    Country: 'NL',
+    Version: '1.0.0',
+    CertificateType: 'Recovery',
+    ValidFrom: '2021-07-01T00:00:00Z',
+    ValidTo: '2030-06-01T00:00:00Z',
+    Logic: {
+      'not-after': [
+        { var: 'external.validationClock' },
+        { plusTime: [{ var: 'payload.r.0.du' }, 0, 'day'] }
+      ]
+    }
+  }])
+
+  const healthCertificate = dcc.parse(TEST_DCC).health_certificate
+
+  test('passed', () => {
+    const results = dcc.validate_business_rules(rules, healthCertificate, {
+      validationClock: '2021-06-01T00:00:00Z',
+      countryCode: 'NL'
+    })
+    expect(results[0].outcome).toBe('Passed')
+  })
+
+  test('failed', () => {
+    const results = dcc.validate_business_rules(rules, healthCertificate, {
+      validationClock: '2022-01-01T00:00:00Z',
+      countryCode: 'NL'
+    })
+    expect(results[0].outcome).toBe('Failed')
+  })
+
+  test('rule store', () => {
+    const ruleStore = new dcc.RuleStore()
+    ruleStore.add_rules(rules)
+
+    expect(ruleStore.select('2021-08-01T00:00:00Z', 'NL', undefined, dcc.CertificateType.Recovery).length).toBe(1)
+    expect(ruleStore.select('2021-08-01T00:00:00Z', 'NL', undefined, dcc.CertificateType.Test).length).toBe(0)
+    expect(ruleStore.select('2021-06-01T00:00:00Z', 'NL', undefined, dcc.CertificateType.Recovery).length).toBe(0)
+    expect(ruleStore.select('2021-08-01T00:00:00Z', 'DE', undefined, dcc.CertificateType.Recovery, 'NL').length).toBe(0)
+
+    const results = ruleStore.validate(healthCertificate, {
+      validationClock: '2021-07-02T00:00:00Z',
+      countryCode: 'NL'
+    })
+    expect(results[0].outcome).toBe('Failed')
+  })
+
+  test('rules in the options', () => {
+    const ruleStore = new dcc.RuleStore()
+    ruleStore.add_rules(rules)
+
+    const options = new dcc.ParseOptions()
+    options.set_now(Date.parse('2022-01-01T00:00:00Z') / 1000)
+    options.set_rules(ruleStore, 'NL')
+
+    const result = dcc.parse_with_options(TEST_DCC, options)
+    expect(result.rule_results[0].outcome).toBe('Failed')
+  })
+})
+
+describe('Status', () => {
+
+  test('not parseable', () => {
+    expect(dcc.parse('INVALID_DATA').status).toBe(dcc.Status.NotParseable)
+  })
+
+  test('unknown issuer', () => {
+    const result = dcc.parse(TEST_DCC)
+    expect(result.status).toBe(dcc.Status.UnknownIssuer)
+    expect(result.status_name).toBe('UNKNOWN_ISSUER')
+  })
+})
+
+describe('Revocation', () => {
+
+  const sha256 = (data) => crypto.createHash('sha256').update(data).digest().subarray(0, 16).toString('base64')
+
+  test('not revoked by default', () => {
+    expect(dcc.parse(TEST_DCC).revoked).toBe(false)
+  })
+
+  test('revoked by UCI', () => {
+    const generator = new dcc.TestDataGenerator('DE', Date.now() / 1000)
+    const fixture = generator.fixture(dcc.FixtureKind.Valid, dcc.parse(TEST_DCC).health_certificate)
+    const trustStore = new dcc.TrustStore()
+    trustStore.add_certificate(fixture.kid, fixture.certificate)
+    const revocationStore = new dcc.RevocationStore()
+    revocationStore.add_hash(fixture.kid, dcc.HashType.Uci, sha256('URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W'))
+
+    const options = new dcc.ParseOptions()
+    options.set_trust_store(trustStore)
+    options.set_revocation_store(revocationStore)
+    const result = dcc.parse_with_options(fixture.dcc, options)
+
+    expect(result.signature_valid).toBe(true)
+    expect(result.revoked).toBe(true)
+    expect(result.error_code).toBe(dcc.ErrorCode.Revoked)
+    expect(result.status).toBe(dcc.Status.Revoked)
+  })
+})
+
+describe('Issuing', () => {
+
+  const healthCertificate = dcc.parse(TEST_DCC).health_certificate
+
+  test('es256', () => {
+    const { privateKey } = crypto.generateKeyPairSync('ec', {
+      namedCurve: 'P-256',
+      privateKeyEncoding: { type: 'pkcs8', format: 'der' },
+      publicKeyEncoding: { type: 'spki', format: 'der' },
+    })
+
+    const issued = dcc.Issuer.es256(privateKey, 'AQIDBAUGBwg=').issue(healthCertificate, 'DE', 1622316073, 1643356073)
+    const result = dcc.parse(issued)
+
+    expect(issued.startsWith('HC1:')).toBe(true)
+    expect(result.successful).toBe(true)
+    expect(result.kid).toBe('AQIDBAUGBwg=')
+    expect(result.algorithm).toBe(-7)
+    expect(result.claims.issuer).toBe('DE')
+    expect(result.health_certificate).toEqual(healthCertificate)
+  })
+
+  test('invalid key', () => {
+    expect(() => dcc.Issuer.es256(new Uint8Array([1, 2, 3]), 'AQIDBAUGBwg=')).toThrow()
+  })
+})
+
+describe('Test data', () => {
+
+  const generator = new dcc.TestDataGenerator('DE', Date.now() / 1000)
+  const healthCertificate = dcc.parse(TEST_DCC).health_certificate
+
+  const parseFixture = (kind) => {
+    const fixture = generator.fixture(kind, healthCertificate)
+    const trustStore = new dcc.TrustStore()
+    trustStore.add_certificate(fixture.kid, fixture.certificate)
+
+    const options = new dcc.ParseOptions()
+    options.set_trust_store(trustStore)
+    return dcc.parse_with_options(fixture.dcc, options)
+  }
+
+  test('valid', () => {
+    const result = parseFixture(dcc.FixtureKind.Valid)
+    expect(result.signature_valid).toBe(true)
+    expect(result.validity).toBe(dcc.Validity.Valid)
+    expect(result.status).toBe(dcc.Status.Valid)
+  })
+
+  test('expired', () => {
+    const result = parseFixture(dcc.FixtureKind.Expired)
+    expect(result.signature_valid).toBe(true)
+    expect(result.validity).toBe(dcc.Validity.Expired)
+    expect(result.error_code).toBe(dcc.ErrorCode.Expired)
+    expect(result.status).toBe(dcc.Status.Expired)
+  })
+
+  test('tampered', () => {
+    const result = parseFixture(dcc.FixtureKind.Tampered)
+    expect(result.signature_valid).toBe(false)
+    expect(result.error_name).toBe('bad-signature')
+  })
+
+  test('batch', () => {
+    const fixture = generator.fixture(dcc.FixtureKind.Valid, healthCertificate)
+    const trustStore = new dcc.TrustStore()
+    trustStore.add_certificate(fixture.kid, fixture.certificate)
+    const options = new dcc.ParseOptions()
+    options.set_trust_store(trustStore)
+
+    const batch = dcc.verify_batch([fixture.dcc, fixture.dcc, 'INVALID_DATA'], options)
+    expect(batch.length).toBe(3)
+    expect(batch.statuses).toEqual(new Uint8Array([dcc.Status.Valid, dcc.Status.Valid, dcc.Status.NotParseable]))
+    expect(batch.error_codes[2]).toBe(dcc.ErrorCode.Base45)
+    expect(batch.result(0).signature_valid).toBe(true)
+    expect(batch.statistics.issuer_countries.DE).toBe(2)
+    expect(batch.statistics.statuses.VALID).toBe(2)
+  })
+
+  test('wrong country', () => {
+    const result = parseFixture(dcc.FixtureKind.WrongCountry)
+    expect(result.claims.issuer).toBe('XX')
+    expect(result.error_code).toBe(dcc.ErrorCode.WrongCountry)
+    expect(result.status).toBe(dcc.Status.Invalid)
+  })
+
+  test('wrong extended key usage', () => {
+    const result = parseFixture(dcc.FixtureKind.WrongEku)
+    expect(result.signature_valid).toBe(true)
+    expect(result.error_code).toBe(dcc.ErrorCode.WrongCertificateType)
+    expect(result.status).toBe(dcc.Status.Invalid)
+  })
+})
+
 describe('Try to parse invalid dcc', () => {
 
   const result = dcc.parse('INVALID_DATA')
@@ -67,4 +376,14 @@ describe('Try to parse invalid dcc', () => {
   test('error message', () => {
     expect(result.error.length).toBeGreaterThan(0)
   })
+
+  test('error code', () => {
+    expect(result.error_code).toBe(dcc.ErrorCode.Base45)
+    expect(result.error_name).toBe('base45')
+  })
+
+  test('decode error', () => {
+    expect(result.decode_error.code).toBe(dcc.ErrorCode.Base45)
+    expect(result.verification_error).toBeUndefined()
+  })
 })
\ No newline at end of file
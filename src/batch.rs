@@ -0,0 +1,153 @@
+use crate::decoded_dcc::DecodedDcc;
+use crate::parse_options::ParseOptions;
+use crate::parsing_result::ParsingResult;
+use crate::status::Status;
+use crate::to_js_value;
+use crate::verifier::DccVerifier;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+///
+/// Results of verifying many DCCs
+///
+/// The result of each DCC in the order of the input, and the
+/// statistics over all results.
+///
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BatchResult {
+    pub results: Vec<DecodedDcc>,
+    pub statistics: BatchStatistics,
+}
+
+///
+/// Counts of the results of a batch
+///
+/// Only DCCs with an issuer claim are counted per issuer country,
+/// and only DCCs with a kid per kid.
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BatchStatistics {
+    /// Number of DCCs
+    pub total: usize,
+    /// Number of DCCs per status
+    pub statuses: BTreeMap<Status, usize>,
+    /// Number of DCCs per country of the issuer
+    pub issuer_countries: BTreeMap<String, usize>,
+    /// Number of DCCs per kid
+    pub kids: BTreeMap<String, usize>,
+}
+
+impl BatchResult {
+    /// The results with their statistics
+    pub fn new(results: Vec<DecodedDcc>) -> BatchResult {
+        let statistics = BatchStatistics::of(&results);
+
+        BatchResult {
+            results,
+            statistics,
+        }
+    }
+}
+
+impl BatchStatistics {
+    /// Count the results per status, issuer country and kid
+    pub fn of(results: &[DecodedDcc]) -> BatchStatistics {
+        let mut statistics = BatchStatistics {
+            total: results.len(),
+            ..BatchStatistics::default()
+        };
+
+        for decoded in results {
+            *statistics.statuses.entry(decoded.status).or_insert(0) += 1;
+
+            if let Some(issuer) = &decoded.claims.issuer {
+                *statistics
+                    .issuer_countries
+                    .entry(issuer.clone())
+                    .or_insert(0) += 1;
+            }
+
+            if !decoded.kid.is_empty() {
+                *statistics.kids.entry(decoded.kid.clone()).or_insert(0) += 1;
+            }
+        }
+
+        statistics
+    }
+}
+
+///
+/// Verify many European Digital Covid Certificates (DCC)
+///
+/// Same as `parse_with_options` for each DCC, but the certificates of
+/// the trust store are loaded once for all DCCs.
+///
+#[wasm_bindgen]
+pub fn verify_batch(dcc_certificates: Vec<String>, options: &ParseOptions) -> JsBatchResult {
+    JsBatchResult {
+        batch: DccVerifier::with_options(options.clone()).verify_batch(&dcc_certificates),
+    }
+}
+
+///
+/// Results of verifying many DCCs in javascript
+///
+/// The statuses and error codes of all DCCs are typed arrays, the full
+/// ParsingResult of a DCC is created on request.
+///
+#[wasm_bindgen(js_name = BatchResult)]
+pub struct JsBatchResult {
+    batch: BatchResult,
+}
+
+#[wasm_bindgen(js_class = BatchResult)]
+impl JsBatchResult {
+    /// Number of DCCs
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.batch.results.len()
+    }
+
+    /// The Status of each DCC
+    #[wasm_bindgen(getter)]
+    pub fn statuses(&self) -> Vec<u8> {
+        self.batch
+            .results
+            .iter()
+            .map(|decoded| decoded.status as u8)
+            .collect()
+    }
+
+    /// The ErrorCode of each DCC
+    #[wasm_bindgen(getter)]
+    pub fn error_codes(&self) -> Vec<u8> {
+        self.batch
+            .results
+            .iter()
+            .map(|decoded| decoded.error_code as u8)
+            .collect()
+    }
+
+    /// The ParsingResult of the DCC at an index
+    pub fn result(&self, index: usize) -> Option<ParsingResult> {
+        self.batch
+            .results
+            .get(index)
+            .map(|decoded| ParsingResult::from(decoded.clone()))
+    }
+
+    /// Number of DCCs per status, issuer country and kid
+    #[wasm_bindgen(getter)]
+    pub fn statistics(&self) -> Result<JsValue, String> {
+        to_js_value(&self.batch.statistics)
+    }
+}
+
+impl JsBatchResult {
+    /// The results of the batch
+    pub fn batch(&self) -> &BatchResult {
+        &self.batch
+    }
+}
@@ -0,0 +1,366 @@
+//! Verify Digital Covid Certificates on the command line
+//!
+//! Decodes and verifies HC1 strings from the arguments, a file or stdin
+//! with the same steps as `parse`, and prints the results as JSON lines
+//! or as a table. The exit code tells whether all certificates are valid.
+
+use chrono::{DateTime, Utc};
+use dcc_wasm::{DccVerifier, DecodedDcc, ErrorCode, ParseOptions, Status, TrustStore};
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "Usage: dcc [OPTIONS] [HC1:...]
+
+Decode and verify Digital Covid Certificates. The certificates are read
+from the arguments, from a file with one certificate per line, or from
+stdin when there are no arguments.
+
+Options:
+  -f, --file <PATH>        Read the certificates from a file, - for stdin
+  -t, --trust-list <PATH>  Trust the certificates of a JSON trust list
+  -o, --output <FORMAT>    Output format: table (default) or json
+      --now <SECONDS>      Moment of verification in seconds since epoch
+  -h, --help               Print this help
+
+Exit codes:
+  0  All certificates are valid
+  1  A certificate is not valid, e.g. expired or with an invalid signature
+  2  A certificate can't be decoded
+  3  Invalid arguments or a file can't be read";
+
+/// All certificates are valid
+const EXIT_VALID: i32 = 0;
+
+/// A certificate is decoded, but not valid
+const EXIT_INVALID: i32 = 1;
+
+/// A certificate can't be decoded
+const EXIT_NOT_PARSEABLE: i32 = 2;
+
+/// Invalid arguments or a file can't be read
+const EXIT_USAGE: i32 = 3;
+
+/// Format of the results
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    Table,
+    Json,
+}
+
+/// The command line arguments
+#[derive(Debug, PartialEq)]
+struct Args {
+    certificates: Vec<String>,
+    file: Option<String>,
+    trust_list: Option<String>,
+    output: Output,
+    now: Option<f64>,
+    help: bool,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("dcc: {}\n\n{}", e, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+/// Verify the certificates and print the results, returns the exit code
+fn run(args: &[String]) -> Result<i32, String> {
+    let args = parse_args(args)?;
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(EXIT_VALID);
+    }
+
+    let verifier = DccVerifier::with_options(parse_options(&args)?);
+    let certificates = read_certificates(&args)?;
+    if certificates.is_empty() {
+        return Err(String::from("No certificates to verify"));
+    }
+
+    let results = verifier.verify_batch(&certificates).results;
+
+    for (index, decoded) in results.iter().enumerate() {
+        match args.output {
+            Output::Json => match serde_json::to_string(decoded) {
+                Ok(json) => println!("{}", json),
+                Err(e) => return Err(format!("Couldn't convert to JSON: {}", e)),
+            },
+            Output::Table => {
+                if index > 0 {
+                    println!();
+                }
+                print_table(decoded);
+            }
+        }
+    }
+
+    Ok(exit_code(&results))
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args {
+        certificates: Vec::new(),
+        file: None,
+        trust_list: None,
+        output: Output::Table,
+        now: None,
+        help: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || match args.next() {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("Missing value for {}", arg)),
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => parsed.help = true,
+            "-f" | "--file" => parsed.file = Some(value()?),
+            "-t" | "--trust-list" => parsed.trust_list = Some(value()?),
+            "-o" | "--output" => {
+                parsed.output = match value()?.as_str() {
+                    "table" => Output::Table,
+                    "json" => Output::Json,
+                    other => return Err(format!("Unknown output format {}", other)),
+                }
+            }
+            "--now" => match value()?.parse() {
+                Ok(now) => parsed.now = Some(now),
+                Err(_e) => return Err(String::from("--now is not a number of seconds")),
+            },
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            certificate => parsed.certificates.push(certificate.to_string()),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// The options to verify, with the certificates of the trust list
+fn parse_options(args: &Args) -> Result<ParseOptions, String> {
+    let mut options = ParseOptions::new();
+
+    if let Some(path) = &args.trust_list {
+        let mut trust_store = TrustStore::builtin();
+        trust_store.add_trust_list(&read_file(path)?)?;
+        options.set_trust_store(&trust_store);
+    }
+
+    if let Some(now) = args.now {
+        options.set_now(now);
+    }
+
+    Ok(options)
+}
+
+/// The certificates of the arguments and the file, or of stdin
+fn read_certificates(args: &Args) -> Result<Vec<String>, String> {
+    let mut certificates = args.certificates.clone();
+
+    match args.file.as_deref() {
+        Some(path) => certificates.extend(lines(&read_file(path)?)),
+        None if certificates.is_empty() => certificates.extend(lines(&read_file("-")?)),
+        None => {}
+    }
+
+    Ok(certificates)
+}
+
+/// Content of a file, or of stdin for -
+fn read_file(path: &str) -> Result<String, String> {
+    let mut content = String::new();
+
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut content).map(|_n| ())
+    } else {
+        fs::read_to_string(path).map(|file| content = file)
+    };
+
+    match read {
+        Ok(()) => Ok(content),
+        Err(e) => Err(format!("Couldn't read {}: {}", path, e)),
+    }
+}
+
+/// The non empty lines, without surrounding whitespace
+fn lines(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The most severe exit code of the results
+fn exit_code(results: &[DecodedDcc]) -> i32 {
+    results
+        .iter()
+        .map(|decoded| match decoded.status {
+            Status::Valid => EXIT_VALID,
+            Status::NotParseable => EXIT_NOT_PARSEABLE,
+            _ => EXIT_INVALID,
+        })
+        .max()
+        .unwrap_or(EXIT_VALID)
+}
+
+/// The result of each decoding stage: ok, failed or skipped
+fn decode_stages(decoded: &DecodedDcc) -> Vec<(&'static str, String)> {
+    let stages = [
+        ("base45", ErrorCode::Base45),
+        ("zlib", ErrorCode::Zlib),
+        ("cbor", ErrorCode::Cbor),
+        ("cose", ErrorCode::CoseStructure),
+    ];
+    let failed = match &decoded.decode_error {
+        Some(e) if e.code == ErrorCode::DetachedPayload || e.code == ErrorCode::Payload => {
+            stages.len()
+        }
+        Some(e) => stages
+            .iter()
+            .position(|(_name, code)| *code == e.code)
+            .unwrap_or(0),
+        None => stages.len() + 1,
+    };
+
+    let mut results: Vec<(&'static str, String)> = stages
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _code))| (*name, stage_result(decoded, index, failed)))
+        .collect();
+    results.push(("payload", stage_result(decoded, stages.len(), failed)));
+    results
+}
+
+fn stage_result(decoded: &DecodedDcc, index: usize, failed: usize) -> String {
+    if index < failed {
+        String::from("ok")
+    } else if index == failed {
+        format!("failed: {}", decoded.error)
+    } else {
+        String::from("skipped")
+    }
+}
+
+/// Name of a COSE algorithm
+fn algorithm_name(algorithm: i32) -> &'static str {
+    match algorithm {
+        -7 => "ES256",
+        -35 => "ES384",
+        -36 => "ES512",
+        -37 => "PS256",
+        _ => "unknown",
+    }
+}
+
+/// Seconds since epoch as date-time in UTC
+fn date_time(seconds: Option<i64>) -> String {
+    seconds
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0))
+        .map(|date_time| date_time.to_rfc3339())
+        .unwrap_or_else(|| String::from("-"))
+}
+
+fn print_table(decoded: &DecodedDcc) {
+    let mut rows = decode_stages(decoded);
+
+    if decoded.successful {
+        rows.push(("kid", decoded.kid.clone()));
+        rows.push((
+            "algorithm",
+            format!(
+                "{} ({})",
+                algorithm_name(decoded.algorithm),
+                decoded.algorithm
+            ),
+        ));
+        rows.push((
+            "issuer",
+            decoded.claims.issuer.clone().unwrap_or_else(|| "-".into()),
+        ));
+        rows.push(("issued at", date_time(decoded.claims.issued_at)));
+        rows.push(("expiration", date_time(decoded.claims.expiration)));
+        rows.push((
+            "signature",
+            match &decoded.verification_error {
+                _ if decoded.signature_valid => String::from("valid"),
+                Some(e) => format!("invalid: {}", e.message),
+                None => String::from("invalid"),
+            },
+        ));
+        rows.push(("validity", format!("{:?}", decoded.validity)));
+        rows.push(("revoked", decoded.revoked.to_string()));
+    }
+
+    rows.push(("status", decoded.status.to_string()));
+    if decoded.error_code != ErrorCode::None {
+        rows.push((
+            "error",
+            format!("{} ({})", decoded.error, decoded.error_code),
+        ));
+    }
+    for warning in &decoded.warnings {
+        rows.push(("warning", warning.clone()));
+    }
+
+    for (name, value) in rows {
+        println!("{:<12}{}", name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn it_parses_the_arguments() {
+        let parsed =
+            parse_args(&args(&["-o", "json", "--trust-list", "dsc.json", "HC1:A"])).unwrap();
+
+        assert_eq!(parsed.output, Output::Json);
+        assert_eq!(parsed.trust_list, Some(String::from("dsc.json")));
+        assert_eq!(parsed.certificates, vec![String::from("HC1:A")]);
+    }
+
+    #[test]
+    fn it_rejects_invalid_arguments() {
+        assert!(parse_args(&args(&["--output", "xml"])).is_err());
+        assert!(parse_args(&args(&["--file"])).is_err());
+        assert!(parse_args(&args(&["--unknown"])).is_err());
+    }
+
+    #[test]
+    fn it_skips_empty_lines() {
+        assert_eq!(lines("HC1:A\n\n  HC1:B  \n"), vec!["HC1:A", "HC1:B"]);
+    }
+
+    #[test]
+    fn the_most_severe_exit_code() {
+        let verifier = DccVerifier::new();
+        let not_parseable = verifier.verify("HC1:INVALID");
+
+        assert_eq!(exit_code(&[]), EXIT_VALID);
+        assert_eq!(
+            exit_code(std::slice::from_ref(&not_parseable)),
+            EXIT_NOT_PARSEABLE
+        );
+        assert_eq!(
+            decode_stages(&not_parseable)[0].1,
+            format!("failed: {}", not_parseable.error)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use sha2::{Digest, Sha256};
+
+/// Number of bits of the ints in which the reference implementation stores the bits
+const INT_BITS: usize = 32;
+
+///
+/// A Bloom filter of revocation hashes
+///
+/// The format of the EU DCC revocation Bloom filters, see
+/// https://github.com/eu-digital-green-certificates/dgc-bloomfilter
+///
+/// Hash function i, for i in 0..k, is the SHA-256 of the element followed
+/// by i as 4 byte big endian int. Its bit index is the hash as unsigned big
+/// endian number modulo the number of bits m. The bits are stored in 32 bit
+/// ints, bit n is `0x80000000 >>> (n % 32)` of int `n / 32`. As big endian
+/// bytes, bit n is `0x80 >> (n % 8)` of byte `n / 8`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    bit_count: u32,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    ///
+    /// A Bloom filter of the data, the ints of the reference implementation
+    /// as big endian bytes, with the number of bits and hash functions
+    ///
+    pub fn from_bits(bits: &[u8], bit_count: u32, hash_count: u32) -> Result<BloomFilter, String> {
+        if bit_count == 0 || hash_count == 0 {
+            return Err(String::from("A Bloom filter needs bits and hash functions"));
+        }
+
+        if bits.len() * 8 < bit_count as usize {
+            return Err(format!(
+                "A Bloom filter of {} bits needs at least {} bytes, got {}",
+                bit_count,
+                (bit_count as usize + 7) / 8,
+                bits.len()
+            ));
+        }
+
+        Ok(BloomFilter {
+            bits: bits.to_vec(),
+            bit_count,
+            hash_count,
+        })
+    }
+
+    ///
+    /// An empty Bloom filter sized for the number of hashes with
+    /// the false positive rate, as the reference implementation does
+    ///
+    pub fn with_rate(expected: usize, false_positive_rate: f64) -> BloomFilter {
+        let n = expected.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-n * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(1.0);
+        let hash_count = (bit_count / n * ln2).round().max(1.0);
+        let int_count = (bit_count as usize + INT_BITS - 1) / INT_BITS;
+
+        BloomFilter {
+            bits: vec![0; int_count * INT_BITS / 8],
+            bit_count: bit_count as u32,
+            hash_count: hash_count as u32,
+        }
+    }
+
+    /// The bits of the filter, the ints as big endian bytes
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// The number of bits used by the hash functions
+    pub fn bit_count(&self) -> u32 {
+        self.bit_count
+    }
+
+    /// The number of hash functions
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    /// Add an element
+    pub fn insert(&mut self, element: &[u8]) {
+        for index in self.indices(element) {
+            self.bits[index / 8] |= 0x80 >> (index % 8);
+        }
+    }
+
+    /// Whether the element is possibly in the filter
+    pub fn contains(&self, element: &[u8]) -> bool {
+        self.indices(element)
+            .all(|index| self.bits[index / 8] & (0x80 >> (index % 8)) != 0)
+    }
+
+    fn indices<'a>(&self, element: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let bit_count = u64::from(self.bit_count);
+
+        (0..self.hash_count).map(move |i| {
+            let hash = Sha256::new()
+                .chain_update(element)
+                .chain_update(i.to_be_bytes())
+                .finalize();
+
+            // The hash as unsigned big endian number modulo the number of bits
+            hash.iter()
+                .fold(0, |rest, byte| (rest * 256 + u64::from(*byte)) % bit_count)
+                as usize
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(i: u32) -> Vec<u8> {
+        Sha256::digest(i.to_be_bytes())[..16].to_vec()
+    }
+
+    #[test]
+    fn it_reads_a_filter_of_the_reference_implementation() {
+        // Elements [1; 16] and [2; 16] with 100 bits and 3 hash functions,
+        // their bit indices are 69, 50, 80 and 88, 14, 67
+        let bits = [
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x14, 0x00, 0x80, 0x80, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        let filter = BloomFilter::from_bits(&bits, 100, 3).unwrap();
+
+        assert!(filter.contains(&[1; 16]));
+        assert!(filter.contains(&[2; 16]));
+        assert!(!filter.contains(&[3; 16]));
+
+        let mut inserted = BloomFilter::from_bits(&[0; 16], 100, 3).unwrap();
+        inserted.insert(&[1; 16]);
+        inserted.insert(&[2; 16]);
+        assert_eq!(inserted, filter);
+    }
+
+    #[test]
+    fn it_contains_the_inserted_hashes() {
+        let mut filter = BloomFilter::with_rate(1000, 0.01);
+        for i in 0..1000 {
+            filter.insert(&hash(i));
+        }
+
+        assert!((0..1000).all(|i| filter.contains(&hash(i))));
+    }
+
+    #[test]
+    fn false_positives_are_rare() {
+        let mut filter = BloomFilter::with_rate(1000, 0.01);
+        for i in 0..1000 {
+            filter.insert(&hash(i));
+        }
+
+        let false_positives = (1000..11000).filter(|i| filter.contains(&hash(*i))).count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn it_is_sized_by_the_rate() {
+        let filter = BloomFilter::with_rate(1000, 0.01);
+
+        assert_eq!(filter.hash_count(), 7);
+        assert_eq!(filter.bit_count(), 9586);
+        assert_eq!(filter.bits().len(), 1200);
+        assert!(BloomFilter::from_bits(&[], 8, 7).is_err());
+        assert!(BloomFilter::from_bits(&[0; 4], 33, 7).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, SecondsFormat,
+    TimeZone, Utc,
+};
+use serde_json::{Map, Value};
+use std::convert::TryInto;
+
+///
+/// Evaluate a CertLogic expression on the data
+///
+/// https://github.com/ehn-dcc-development/dgc-business-rules/blob/main/certlogic/specification/README.md
+///
+/// CertLogic is a subset of JsonLogic, extended with date arithmetic and
+/// operations specific to a DCC. Date-times are represented as strings
+/// according to RFC 3339.
+///
+pub fn evaluate(expression: &Value, data: &Value) -> Result<Value, String> {
+    match expression {
+        Value::String(_) | Value::Number(_) | Value::Bool(_) => Ok(expression.clone()),
+        Value::Null => Err(String::from("null is not a valid expression")),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| evaluate(item, data))
+            .collect::<Result<Vec<Value>, String>>()
+            .map(Value::Array),
+        Value::Object(object) => operation(object, data),
+    }
+}
+
+///
+/// Whether a value is truthy according to CertLogic
+///
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(boolean) => *boolean,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(object) => !object.is_empty(),
+    }
+}
+
+fn operation(object: &Map<String, Value>, data: &Value) -> Result<Value, String> {
+    let (operator, operands) = match object.iter().next() {
+        Some(operation) if object.len() == 1 => operation,
+        _ => return Err(String::from("An operation must have exactly one operator")),
+    };
+
+    if operator == "var" {
+        return var(operands, data);
+    }
+
+    let operands = match operands.as_array() {
+        Some(operands) => operands,
+        None => return Err(format!("Operands of {} are not an array", operator)),
+    };
+
+    match operator.as_str() {
+        "if" => {
+            let [guard, then, otherwise] = exactly::<3>(operator, operands)?;
+            if is_truthy(&evaluate(guard, data)?) {
+                evaluate(then, data)
+            } else {
+                evaluate(otherwise, data)
+            }
+        }
+        "===" => {
+            let [left, right] = exactly::<2>(operator, operands)?;
+            Ok(Value::Bool(evaluate(left, data)? == evaluate(right, data)?))
+        }
+        "and" => {
+            if operands.len() < 2 {
+                return Err(String::from("Operation and needs at least 2 operands"));
+            }
+            let mut result = Value::Bool(true);
+            for operand in operands {
+                result = evaluate(operand, data)?;
+                if !is_truthy(&result) {
+                    break;
+                }
+            }
+            Ok(result)
+        }
+        "!" => {
+            let [operand] = exactly::<1>(operator, operands)?;
+            Ok(Value::Bool(!is_truthy(&evaluate(operand, data)?)))
+        }
+        "<" | ">" | "<=" | ">=" => {
+            let integers = evaluate_all(operator, operands, data)?
+                .iter()
+                .map(|value| integer(operator, value))
+                .collect::<Result<Vec<i64>, String>>()?;
+            Ok(Value::Bool(compare(operator, &integers)?))
+        }
+        "in" => {
+            let [needle, haystack] = exactly::<2>(operator, operands)?;
+            let needle = evaluate(needle, data)?;
+            match evaluate(haystack, data)? {
+                Value::Array(items) => Ok(Value::Bool(items.contains(&needle))),
+                _ => Err(String::from("Second operand of in is not an array")),
+            }
+        }
+        "+" => {
+            let [left, right] = exactly::<2>(operator, operands)?;
+            let sum = integer(operator, &evaluate(left, data)?)?
+                .checked_add(integer(operator, &evaluate(right, data)?)?)
+                .ok_or_else(|| String::from("Addition overflows"))?;
+            Ok(Value::from(sum))
+        }
+        "reduce" => {
+            let [operand, lambda, initial] = exactly::<3>(operator, operands)?;
+            let mut accumulator = evaluate(initial, data)?;
+            let items = match evaluate(operand, data)? {
+                Value::Array(items) => items,
+                Value::Null => return Ok(accumulator),
+                _ => return Err(String::from("First operand of reduce is not an array")),
+            };
+
+            for current in items {
+                let mut context = Map::new();
+                context.insert(String::from("current"), current);
+                context.insert(String::from("accumulator"), accumulator);
+                context.insert(String::from("data"), data.clone());
+                accumulator = evaluate(lambda, &Value::Object(context))?;
+            }
+            Ok(accumulator)
+        }
+        "plusTime" => {
+            let [date_time, amount, unit] = exactly::<3>(operator, operands)?;
+            let date_time = date_time_of(&evaluate(date_time, data)?)?;
+            let amount = match amount.as_i64() {
+                Some(amount) => amount,
+                None => return Err(String::from("Amount of plusTime is not an integer")),
+            };
+            let unit = unit.as_str().unwrap_or_default();
+            Ok(format_date_time(plus_time(date_time, amount, unit)?))
+        }
+        "before" | "not-before" | "after" | "not-after" => {
+            let date_times = evaluate_all(operator, operands, data)?
+                .iter()
+                .map(date_time_of)
+                .collect::<Result<Vec<DateTime<FixedOffset>>, String>>()?;
+            Ok(Value::Bool(compare(operator, &date_times)?))
+        }
+        "extractFromUVCI" => {
+            let [uvci, index] = exactly::<2>(operator, operands)?;
+            let index = match index.as_u64() {
+                Some(index) => index as usize,
+                None => return Err(String::from("Index of extractFromUVCI is not an integer")),
+            };
+            match evaluate(uvci, data)? {
+                Value::Null => Ok(Value::Null),
+                Value::String(uvci) => Ok(extract_from_uvci(&uvci, index)
+                    .map(Value::from)
+                    .unwrap_or(Value::Null)),
+                _ => Err(String::from("UVCI of extractFromUVCI is not a string")),
+            }
+        }
+        "dccDateOfBirth" => {
+            let [date_of_birth] = exactly::<1>(operator, operands)?;
+            match evaluate(date_of_birth, data)? {
+                Value::String(date_of_birth) => {
+                    Ok(format_date_time(dcc_date_of_birth(&date_of_birth)?))
+                }
+                _ => Err(String::from("Operand of dccDateOfBirth is not a string")),
+            }
+        }
+        _ => Err(format!("Unknown operator {}", operator)),
+    }
+}
+
+///
+/// Access the data by a path of keys and array indices separated by dots,
+/// null when the path doesn't exist
+///
+fn var(path: &Value, data: &Value) -> Result<Value, String> {
+    let path = match path {
+        Value::String(path) => path,
+        _ => return Err(String::from("Operand of var is not a string")),
+    };
+
+    if path.is_empty() {
+        return Ok(data.clone());
+    }
+
+    let mut current = data;
+    for key in path.split('.') {
+        let next = match current {
+            Value::Object(object) => object.get(key),
+            Value::Array(array) => key.parse::<usize>().ok().and_then(|i| array.get(i)),
+            _ => None,
+        };
+
+        current = match next {
+            Some(next) => next,
+            None => return Ok(Value::Null),
+        };
+    }
+
+    Ok(current.clone())
+}
+
+/// Exactly N operands
+fn exactly<'a, const N: usize>(
+    operator: &str,
+    operands: &'a [Value],
+) -> Result<[&'a Value; N], String> {
+    let operands: Vec<&Value> = operands.iter().collect();
+    operands
+        .try_into()
+        .map_err(|_e| format!("Operation {} needs {} operands", operator, N))
+}
+
+/// Evaluate 2 or 3 operands of a comparison
+fn evaluate_all(operator: &str, operands: &[Value], data: &Value) -> Result<Vec<Value>, String> {
+    let allowed = match operator {
+        "not-before" | "not-after" => 2..=2,
+        _ => 2..=3,
+    };
+
+    if !allowed.contains(&operands.len()) {
+        return Err(format!(
+            "Operation {} has {} operands",
+            operator,
+            operands.len()
+        ));
+    }
+
+    operands
+        .iter()
+        .map(|operand| evaluate(operand, data))
+        .collect()
+}
+
+/// Compare each operand with the next
+fn compare<T: PartialOrd>(operator: &str, operands: &[T]) -> Result<bool, String> {
+    let ordered = |a: &T, b: &T| match operator {
+        "<" | "before" => Ok(a < b),
+        ">" | "after" => Ok(a > b),
+        "<=" | "not-after" => Ok(a <= b),
+        ">=" | "not-before" => Ok(a >= b),
+        _ => Err(format!("Unknown comparison {}", operator)),
+    };
+
+    for pair in operands.windows(2) {
+        if !ordered(&pair[0], &pair[1])? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn integer(operator: &str, value: &Value) -> Result<i64, String> {
+    match value.as_i64() {
+        Some(integer) => Ok(integer),
+        None => Err(format!(
+            "Operand of {} is not an integer: {}",
+            operator, value
+        )),
+    }
+}
+
+///
+/// Parse a date-time, a date or a date-time without
+/// an offset is in UTC
+///
+pub fn parse_date_time(date_time: &str) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(date_time) {
+        return Ok(date_time);
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_str(date_time, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Ok(date_time);
+    }
+
+    let utc = FixedOffset::east_opt(0).expect("UTC is a valid offset");
+
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(date_time, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(utc.from_utc_datetime(&date_time));
+    }
+
+    match NaiveDate::parse_from_str(date_time, "%Y-%m-%d") {
+        Ok(date) => Ok(utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())),
+        Err(_e) => Err(format!("Invalid date-time {}", date_time)),
+    }
+}
+
+fn date_time_of(value: &Value) -> Result<DateTime<FixedOffset>, String> {
+    match value {
+        Value::String(date_time) => parse_date_time(date_time),
+        _ => Err(format!("Operand is not a date-time: {}", value)),
+    }
+}
+
+fn format_date_time(date_time: DateTime<FixedOffset>) -> Value {
+    Value::from(date_time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+fn plus_time(
+    date_time: DateTime<FixedOffset>,
+    amount: i64,
+    unit: &str,
+) -> Result<DateTime<FixedOffset>, String> {
+    let plus_months = |months: i64| {
+        let absolute = Months::new(months.unsigned_abs().try_into().unwrap_or(u32::MAX));
+        if months < 0 {
+            date_time.checked_sub_months(absolute)
+        } else {
+            date_time.checked_add_months(absolute)
+        }
+    };
+
+    let result = match unit {
+        "year" => plus_months(amount.saturating_mul(12)),
+        "month" => plus_months(amount),
+        "day" => Duration::try_days(amount).and_then(|d| date_time.checked_add_signed(d)),
+        "hour" => Duration::try_hours(amount).and_then(|d| date_time.checked_add_signed(d)),
+        _ => return Err(format!("Unknown time unit {}", unit)),
+    };
+
+    result.ok_or_else(|| String::from("Date-time out of range"))
+}
+
+///
+/// Fragment of a UVCI, after removing the optional URN:UVCI: prefix
+/// and splitting on the separators /, # and :
+///
+pub fn extract_from_uvci(uvci: &str, index: usize) -> Option<String> {
+    let uvci = uvci.strip_prefix("URN:UVCI:").unwrap_or(uvci);
+    uvci.split(['/', '#', ':']).nth(index).map(String::from)
+}
+
+///
+/// A date of birth as date-time, a partial date of birth is
+/// completed with the end of the year or month
+///
+fn dcc_date_of_birth(date_of_birth: &str) -> Result<DateTime<FixedOffset>, String> {
+    let invalid = || format!("Invalid date of birth {}", date_of_birth);
+    let parts = date_of_birth
+        .split('-')
+        .map(|part| part.parse::<u32>().map_err(|_e| invalid()))
+        .collect::<Result<Vec<u32>, String>>()?;
+
+    let date = match parts.as_slice() {
+        [year, month, day] => NaiveDate::from_ymd_opt(*year as i32, *month, *day),
+        [year, month] => last_day_of_month(*year as i32, *month),
+        [year] => NaiveDate::from_ymd_opt(*year as i32, 12, 31),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+    Ok(Utc.from_utc_datetime(&midnight).fixed_offset())
+}
+
+fn last_day_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = first.checked_add_months(Months::new(1))?;
+    next.pred_opt().filter(|last| last.month() == first.month())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(expression: Value, data: Value) -> Value {
+        evaluate(&expression, &data).unwrap()
+    }
+
+    #[test]
+    fn it_accesses_the_data() {
+        let data = json!({"payload": {"v": [{"dn": 2}]}});
+
+        assert_eq!(eval(json!({"var": "payload.v.0.dn"}), data.clone()), 2);
+        assert_eq!(
+            eval(json!({"var": "payload.t.0.tt"}), data.clone()),
+            Value::Null
+        );
+        assert_eq!(eval(json!({"var": ""}), data.clone()), data);
+    }
+
+    #[test]
+    fn it_evaluates_logic() {
+        let data = json!({"dn": 2, "sd": 2, "mp": "EU/1/20/1528"});
+
+        let complete = json!({"and": [
+            {">=": [{"var": "dn"}, {"var": "sd"}]},
+            {"in": [{"var": "mp"}, ["EU/1/20/1528", "EU/1/20/1507"]]}
+        ]});
+        assert_eq!(eval(complete, data.clone()), true);
+
+        let partial = json!({"if": [{"<": [{"var": "dn"}, {"var": "sd"}]}, "partial", "complete"]});
+        assert_eq!(eval(partial, data.clone()), "complete");
+
+        assert_eq!(eval(json!({"!": [{"var": "t"}]}), data.clone()), true);
+        assert_eq!(eval(json!({"+": [{"var": "dn"}, 1]}), data), 3);
+    }
+
+    #[test]
+    fn it_compares_dates_with_plus_time() {
+        let data = json!({"dt": "2021-06-01", "clock": "2021-06-20T12:00:00Z"});
+
+        let valid = json!({"not-before": [
+            {"var": "clock"},
+            {"plusTime": [{"var": "dt"}, 14, "day"]}
+        ]});
+        assert_eq!(eval(valid, data.clone()), true);
+
+        assert_eq!(
+            eval(
+                json!({"plusTime": [{"var": "dt"}, -1, "year"]}),
+                data.clone()
+            ),
+            "2020-06-01T00:00:00Z"
+        );
+
+        let between =
+            json!({"before": ["2021-06-01", {"var": "clock"}, "2021-07-01T00:00:00+02:00"]});
+        assert_eq!(eval(between, data), true);
+    }
+
+    #[test]
+    fn it_reduces_an_array() {
+        let sum = json!({"reduce": [
+            {"var": "doses"},
+            {"+": [{"var": "accumulator"}, {"var": "current"}]},
+            0
+        ]});
+
+        assert_eq!(eval(sum.clone(), json!({"doses": [1, 2, 3]})), 6);
+        assert_eq!(eval(sum, json!({})), 0);
+    }
+
+    #[test]
+    fn it_extracts_from_the_uvci() {
+        let uvci = "URN:UVCI:01:NL:187/37512422923";
+        assert_eq!(extract_from_uvci(uvci, 1), Some(String::from("NL")));
+        assert_eq!(
+            extract_from_uvci(uvci, 3),
+            Some(String::from("37512422923"))
+        );
+        assert_eq!(extract_from_uvci(uvci, 4), None);
+
+        let data = json!({"ci": Value::Null});
+        assert_eq!(
+            eval(json!({"extractFromUVCI": [{"var": "ci"}, 1]}), data),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn it_completes_a_partial_date_of_birth() {
+        let dob = |dob: &str| eval(json!({"dccDateOfBirth": [dob]}), Value::Null);
+
+        assert_eq!(dob("1990"), "1990-12-31T00:00:00Z");
+        assert_eq!(dob("2000-02"), "2000-02-29T00:00:00Z");
+        assert_eq!(dob("1990-05-17"), "1990-05-17T00:00:00Z");
+    }
+
+    #[test]
+    fn invalid_expressions_are_error() {
+        assert!(evaluate(&json!({"unknown": [1]}), &Value::Null).is_err());
+        assert!(evaluate(&json!({"<": [1, "2"]}), &Value::Null).is_err());
+        assert!(evaluate(&json!({"===": [1]}), &Value::Null).is_err());
+        assert!(evaluate(&json!({"before": ["yesterday", "today"]}), &Value::Null).is_err());
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(!is_truthy(&json!(0)));
+        assert!(!is_truthy(&json!("")));
+        assert!(!is_truthy(&json!([])));
+        assert!(is_truthy(&json!({"a": 1})));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::hcert::HealthCertificate;
+use crate::rules::CertificateType;
+use crate::to_js_value;
+use ciborium::value::Value;
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+///
+/// CWT claims of a Digital Covid Certificate
+///
+/// https://github.com/ehn-dcc-development/hcert-spec/blob/main/hcert_spec.md#331-cwt-structure-overview
+///
+/// Claims which are not known are not part of this struct, but remain
+/// available in the `data` of the ParsingResult.
+///
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CwtClaims {
+    /// Issuer of the DCC, a country code (claim 1)
+    #[wasm_bindgen(getter_with_clone)]
+    pub issuer: Option<String>,
+
+    /// Seconds since epoch when the DCC was issued (claim 6)
+    #[wasm_bindgen(skip)]
+    pub issued_at: Option<i64>,
+
+    /// Seconds since epoch when the DCC expires (claim 4)
+    #[wasm_bindgen(skip)]
+    pub expiration: Option<i64>,
+
+    /// The health certificate (claim -260, key 1)
+    #[wasm_bindgen(skip)]
+    pub hcert: Value,
+}
+
+///
+/// Claim keys
+///
+enum Claim {
+    Issuer,
+    Expiration,
+    IssuedAt,
+    HealthCertificate,
+}
+
+impl Claim {
+    /// Key of the claim in the CWT map
+    fn key(&self) -> i64 {
+        match *self {
+            Claim::Issuer => 1,
+            Claim::Expiration => 4,
+            Claim::IssuedAt => 6,
+            Claim::HealthCertificate => -260,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CwtClaims {
+    /// Seconds since epoch when the DCC was issued
+    #[wasm_bindgen(getter = issued_at)]
+    pub fn issued_at_js(&self) -> Option<f64> {
+        self.issued_at.map(|issued_at| issued_at as f64)
+    }
+
+    /// Seconds since epoch when the DCC expires
+    #[wasm_bindgen(getter = expiration)]
+    pub fn expiration_js(&self) -> Option<f64> {
+        self.expiration.map(|expiration| expiration as f64)
+    }
+
+    /// The health certificate
+    #[wasm_bindgen(getter = hcert)]
+    pub fn hcert_js(&self) -> JsValue {
+        to_js_value(&self.hcert).unwrap_or(JsValue::NULL)
+    }
+}
+
+impl CwtClaims {
+    /// Claims of a health certificate to be issued
+    pub fn new(
+        issuer: &str,
+        issued_at: i64,
+        expiration: i64,
+        health_certificate: &HealthCertificate,
+    ) -> Result<CwtClaims, String> {
+        Ok(CwtClaims {
+            issuer: Some(String::from(issuer)),
+            issued_at: Some(issued_at),
+            expiration: Some(expiration),
+            hcert: health_certificate.to_cbor()?,
+        })
+    }
+
+    /// The claims as CBOR map, the payload of the COSE message.
+    /// Claims which are none are left out.
+    pub fn to_cbor(&self) -> Value {
+        let mut claims: Vec<(Value, Value)> = Vec::new();
+
+        if let Some(issuer) = &self.issuer {
+            claims.push((
+                Value::from(Claim::Issuer.key()),
+                Value::from(issuer.clone()),
+            ));
+        }
+        if let Some(expiration) = self.expiration {
+            claims.push((
+                Value::from(Claim::Expiration.key()),
+                Value::from(expiration),
+            ));
+        }
+        if let Some(issued_at) = self.issued_at {
+            claims.push((Value::from(Claim::IssuedAt.key()), Value::from(issued_at)));
+        }
+        if !self.hcert.is_null() {
+            claims.push((
+                Value::from(Claim::HealthCertificate.key()),
+                Value::Map(vec![(Value::from(1), self.hcert.clone())]),
+            ));
+        }
+
+        Value::Map(claims)
+    }
+
+    /// The health certificate according to the eu-dcc-schema
+    pub fn health_certificate(&self) -> Result<HealthCertificate, String> {
+        HealthCertificate::try_from(&self.hcert)
+    }
+
+    /// Type of the health certificate, by the group it contains
+    pub fn certificate_type(&self) -> Option<CertificateType> {
+        if field(&self.hcert, "v").is_some() {
+            Some(CertificateType::Vaccination)
+        } else if field(&self.hcert, "t").is_some() {
+            Some(CertificateType::Test)
+        } else if field(&self.hcert, "r").is_some() {
+            Some(CertificateType::Recovery)
+        } else {
+            None
+        }
+    }
+
+    /// The unique certificate identifier (ci) of the first vaccination,
+    /// test or recovery entry. It's read from the hcert as is, so it's also
+    /// found when the health certificate doesn't match the schema.
+    pub fn certificate_identifier(&self) -> Option<&str> {
+        ["v", "t", "r"].iter().find_map(|group| {
+            let entry = field(&self.hcert, group)?.as_array()?.first()?;
+            field(entry, "ci")?.as_text()
+        })
+    }
+}
+
+impl Default for CwtClaims {
+    fn default() -> Self {
+        CwtClaims {
+            issuer: None,
+            issued_at: None,
+            expiration: None,
+            hcert: Value::Null,
+        }
+    }
+}
+
+impl TryFrom<&Value> for CwtClaims {
+    type Error = String;
+
+    fn try_from(payload: &Value) -> Result<Self, Self::Error> {
+        let claims = match payload.as_map() {
+            Some(map) => map,
+            None => return Err(String::from("Payload is not a map of CWT claims")),
+        };
+
+        let issuer = match claim(claims, Claim::Issuer) {
+            Some(Value::Text(issuer)) => Some(issuer.clone()),
+            Some(_) => return Err(String::from("Issuer is not a text value")),
+            None => None,
+        };
+
+        let issued_at =
+            numeric_date(claims, Claim::IssuedAt).map_err(|e| format!("Issued at {}", e))?;
+
+        let expiration =
+            numeric_date(claims, Claim::Expiration).map_err(|e| format!("Expiration {}", e))?;
+
+        let hcert = match claim(claims, Claim::HealthCertificate) {
+            Some(Value::Map(hcert)) => hcert
+                .iter()
+                .find(|entry| entry.0 == Value::from(1))
+                .map(|entry| entry.1.clone())
+                .unwrap_or(Value::Null),
+            Some(_) => return Err(String::from("Health certificate is not a map")),
+            None => Value::Null,
+        };
+
+        Ok(CwtClaims {
+            issuer,
+            issued_at,
+            expiration,
+            hcert,
+        })
+    }
+}
+
+///
+/// Get a claim from the claims
+///
+fn claim(claims: &[(Value, Value)], claim: Claim) -> Option<&Value> {
+    claims
+        .iter()
+        .find(|&c| c.0 == Value::from(claim.key()))
+        .map(|c| &c.1)
+}
+
+///
+/// Get a field of a CBOR map by its text key
+///
+fn field<'a>(map: &'a Value, key: &str) -> Option<&'a Value> {
+    map.as_map()?
+        .iter()
+        .find(|entry| entry.0.as_text() == Some(key))
+        .map(|entry| &entry.1)
+}
+
+///
+/// Get a claim containing a NumericDate, seconds since epoch
+///
+fn numeric_date(claims: &[(Value, Value)], key: Claim) -> Result<Option<i64>, String> {
+    match claim(claims, key) {
+        Some(Value::Integer(date)) => match (*date).try_into() {
+            Ok(date) => Ok(Some(date)),
+            Err(_e) => Err(String::from("is out of range")),
+        },
+        Some(Value::Float(date)) => Ok(Some(*date as i64)),
+        Some(_) => Err(String::from("is not a numeric date")),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_dcc::read_dcc;
+
+    const TEST_DCC: &str = "6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1";
+
+    fn test_claims() -> CwtClaims {
+        CwtClaims::try_from(read_dcc(TEST_DCC).unwrap().payload().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_reads_the_claims() {
+        let claims = test_claims();
+
+        assert_eq!(claims.issuer, Some(String::from("DE")));
+        assert_eq!(claims.issued_at, Some(1622316073));
+        assert_eq!(claims.expiration, Some(1643356073));
+    }
+
+    #[test]
+    fn it_reads_the_hcert() {
+        let hcert = test_claims().hcert;
+        let version = hcert
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.0 == Value::from("ver"))
+            .map(|entry| entry.1.clone());
+
+        assert_eq!(version, Some(Value::from("1.0.0")))
+    }
+
+    #[test]
+    fn it_reads_the_certificate_identifier() {
+        assert_eq!(
+            test_claims().certificate_identifier(),
+            Some("URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W")
+        );
+        assert_eq!(CwtClaims::default().certificate_identifier(), None);
+    }
+
+    #[test]
+    fn claims_survive_a_round_trip() {
+        let claims = test_claims();
+        assert_eq!(CwtClaims::try_from(&claims.to_cbor()).unwrap(), claims)
+    }
+
+    #[test]
+    fn missing_claims_are_none() {
+        let claims = CwtClaims::try_from(&Value::Map(vec![])).unwrap();
+        assert_eq!(claims, CwtClaims::default())
+    }
+
+    #[test]
+    fn payload_not_a_map_is_error() {
+        assert!(CwtClaims::try_from(&Value::from("claims")).is_err())
+    }
+
+    #[test]
+    fn invalid_expiration_is_error() {
+        let payload = Value::Map(vec![(Value::from(4), Value::from("tomorrow"))]);
+        assert!(CwtClaims::try_from(&payload).is_err())
+    }
+}
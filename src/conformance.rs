@@ -0,0 +1,614 @@
+use crate::certlogic::parse_date_time;
+use crate::claims::CwtClaims;
+use crate::cose::CoseSingleSigned;
+use crate::parse_options::ParseOptions;
+use crate::read_dcc::{base45_decode, decode_cose, decompress};
+use crate::schema::validate_schema;
+use crate::trust_store::TrustStore;
+use crate::validity::{check_validity, Validity};
+use crate::verify::{verify_signature_with_keys, KeyCache};
+use ciborium::value::Value;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+use std::convert::TryFrom;
+use std::fmt;
+
+///
+/// A test case in the format of the DCC quality assurance test data
+///
+/// https://github.com/eu-digital-green-certificates/dgc-testdata
+///
+/// Every stage of the encoding is optional, the binary stages are hex
+/// encoded. A stage which is missing is taken from the previous stage.
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TestCase {
+    #[serde(rename = "JSON")]
+    pub json: Option<JsonValue>,
+    #[serde(rename = "CBOR")]
+    pub cbor: Option<String>,
+    #[serde(rename = "COSE")]
+    pub cose: Option<String>,
+    #[serde(rename = "COMPRESSED")]
+    pub compressed: Option<String>,
+    #[serde(rename = "BASE45")]
+    pub base45: Option<String>,
+    #[serde(rename = "PREFIX")]
+    pub prefix: Option<String>,
+    #[serde(rename = "2DCODE")]
+    pub qr_code: Option<String>,
+    #[serde(rename = "TESTCTX", default)]
+    pub context: TestContext,
+    #[serde(rename = "EXPECTEDRESULTS", default)]
+    pub expected: ExpectedResults,
+}
+
+///
+/// The context of a test case
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct TestContext {
+    #[serde(default, deserialize_with = "number_or_text")]
+    pub version: Option<u32>,
+    pub schema: Option<String>,
+    /// The DSC, base64 encoded DER
+    pub certificate: Option<String>,
+    /// The moment of validation, RFC 3339
+    #[serde(rename = "VALIDATIONCLOCK")]
+    pub validation_clock: Option<String>,
+    pub description: Option<String>,
+}
+
+///
+/// A number, which is text in some test cases
+///
+fn number_or_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrText {
+        Number(u32),
+        Text(String),
+    }
+
+    match Option::<NumberOrText>::deserialize(deserializer)? {
+        Some(NumberOrText::Number(number)) => Ok(Some(number)),
+        Some(NumberOrText::Text(text)) => match text.parse() {
+            Ok(number) => Ok(Some(number)),
+            Err(_e) => Err(serde::de::Error::custom(format!(
+                "{} is not a number",
+                text
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+///
+/// The expected outcome of each stage, a stage without expectation is not checked
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ExpectedResults {
+    #[serde(rename = "EXPECTEDVALIDOBJECT")]
+    pub valid_object: Option<bool>,
+    #[serde(rename = "EXPECTEDSCHEMAVALIDATION")]
+    pub schema_validation: Option<bool>,
+    #[serde(rename = "EXPECTEDENCODE")]
+    pub encode: Option<bool>,
+    #[serde(rename = "EXPECTEDDECODE")]
+    pub decode: Option<bool>,
+    #[serde(rename = "EXPECTEDVERIFY")]
+    pub verify: Option<bool>,
+    #[serde(rename = "EXPECTEDCOMPRESSION")]
+    pub compression: Option<bool>,
+    #[serde(rename = "EXPECTEDKEYUSAGE")]
+    pub key_usage: Option<bool>,
+    #[serde(rename = "EXPECTEDUNPREFIX")]
+    pub unprefix: Option<bool>,
+    #[serde(rename = "EXPECTEDVALIDJSON")]
+    pub valid_json: Option<bool>,
+    #[serde(rename = "EXPECTEDB45DECODE")]
+    pub base45_decode: Option<bool>,
+    #[serde(rename = "EXPECTEDPICTUREDECODE")]
+    pub picture_decode: Option<bool>,
+    #[serde(rename = "EXPECTEDEXPIRATIONCHECK")]
+    pub expiration_check: Option<bool>,
+}
+
+///
+/// A stage of decoding and verifying a DCC
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    PictureDecode,
+    Unprefix,
+    Base45Decode,
+    Compression,
+    Decode,
+    Verify,
+    ValidObject,
+    ValidJson,
+    SchemaValidation,
+    ExpirationCheck,
+    KeyUsage,
+    Encode,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Stage::PictureDecode => "EXPECTEDPICTUREDECODE",
+            Stage::Unprefix => "EXPECTEDUNPREFIX",
+            Stage::Base45Decode => "EXPECTEDB45DECODE",
+            Stage::Compression => "EXPECTEDCOMPRESSION",
+            Stage::Decode => "EXPECTEDDECODE",
+            Stage::Verify => "EXPECTEDVERIFY",
+            Stage::ValidObject => "EXPECTEDVALIDOBJECT",
+            Stage::ValidJson => "EXPECTEDVALIDJSON",
+            Stage::SchemaValidation => "EXPECTEDSCHEMAVALIDATION",
+            Stage::ExpirationCheck => "EXPECTEDEXPIRATIONCHECK",
+            Stage::KeyUsage => "EXPECTEDKEYUSAGE",
+            Stage::Encode => "EXPECTEDENCODE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///
+/// Outcome of a stage compared to the expectation
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageResult {
+    pub stage: Stage,
+    pub expected: bool,
+    /// Whether the stage succeeded, none if the stage isn't supported
+    /// or its input is missing
+    pub actual: Option<bool>,
+    /// Why the stage failed
+    pub message: Option<String>,
+}
+
+impl StageResult {
+    /// The stage ran and the outcome matches the expectation
+    pub fn passed(&self) -> bool {
+        self.actual == Some(self.expected)
+    }
+
+    /// The stage ran and the outcome doesn't match the expectation
+    pub fn failed(&self) -> bool {
+        self.actual.is_some_and(|actual| actual != self.expected)
+    }
+
+    /// The stage isn't supported or its input is missing
+    pub fn skipped(&self) -> bool {
+        self.actual.is_none()
+    }
+}
+
+impl fmt::Display for StageResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.actual {
+            None => write!(f, "{}: skipped", self.stage),
+            Some(actual) => write!(
+                f,
+                "{}: expected {}, got {}{}",
+                self.stage,
+                self.expected,
+                actual,
+                self.message
+                    .as_ref()
+                    .map(|message| format!(" ({})", message))
+                    .unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl TestCase {
+    /// A test case from JSON
+    pub fn from_json(json: &str) -> Result<TestCase, String> {
+        match serde_json::from_str(json) {
+            Ok(test_case) => Ok(test_case),
+            Err(e) => Err(format!("Invalid test case: {}", e)),
+        }
+    }
+
+    ///
+    /// Run each stage which has an expectation. The picture decode, key
+    /// usage and encode stages are not supported and always skipped.
+    ///
+    pub fn run(&self) -> Vec<StageResult> {
+        let mut runner = Runner {
+            results: Vec::new(),
+        };
+        let expected = &self.expected;
+
+        runner.skip(Stage::PictureDecode, expected.picture_decode);
+
+        // HC1: prefix
+        let base45 = match &self.prefix {
+            Some(prefix) => {
+                let unprefixed = prefix.strip_prefix("HC1:");
+                let matches = match (unprefixed, &self.base45) {
+                    (Some(unprefixed), Some(base45)) => unprefixed == base45,
+                    (unprefixed, _) => unprefixed.is_some(),
+                };
+                runner.check(
+                    Stage::Unprefix,
+                    expected.unprefix,
+                    if matches {
+                        Ok(())
+                    } else {
+                        Err(String::from("Prefix doesn't match the base45 data"))
+                    },
+                );
+                self.base45.clone().or_else(|| unprefixed.map(String::from))
+            }
+            None => self.base45.clone(),
+        };
+
+        // Base45
+        let compressed = self.compressed.as_deref().and_then(|c| hex_decode(c).ok());
+        let compressed = match base45 {
+            Some(base45) => {
+                let decoded = base45_decode(&base45)
+                    .map_err(String::from)
+                    .and_then(|decoded| {
+                        equals(&decoded, compressed.as_ref(), "compressed data")?;
+                        Ok(decoded)
+                    });
+                runner.check(Stage::Base45Decode, expected.base45_decode, decoded.clone());
+                compressed.or_else(|| decoded.ok())
+            }
+            None => compressed,
+        };
+
+        // Zlib
+        let cose_bytes = self.cose.as_deref().and_then(|c| hex_decode(c).ok());
+        let cose_bytes = match compressed {
+            Some(compressed) => {
+                let decompressed =
+                    decompress(&compressed)
+                        .map_err(String::from)
+                        .and_then(|decompressed| {
+                            equals(&decompressed, cose_bytes.as_ref(), "COSE data")?;
+                            Ok(decompressed)
+                        });
+                runner.check(
+                    Stage::Compression,
+                    expected.compression,
+                    decompressed.clone(),
+                );
+                cose_bytes.or_else(|| decompressed.ok())
+            }
+            None => cose_bytes,
+        };
+
+        // COSE, the CBOR of the test case is the payload or the health certificate in it
+        let cbor = self.cbor.as_deref().map(|cbor| {
+            hex_decode(cbor).and_then(|cbor| {
+                ciborium::de::from_reader::<Value, _>(&cbor[..])
+                    .map_err(|e| format!("Invalid CBOR data: {}", e))
+            })
+        });
+        let cose = match cose_bytes.as_deref().map(decode_cose) {
+            Some(cose) => {
+                let cose = cose.map_err(String::from).and_then(|cose| {
+                    // Compared as JSON, the order of the keys may differ
+                    if let Some(cbor) = &cbor {
+                        let cbor = json(&cbor.clone()?);
+                        let payload = payload_of(&cose)?;
+                        let hcert = CwtClaims::try_from(payload)
+                            .ok()
+                            .and_then(|claims| json(&claims.hcert));
+                        if cbor != json(payload) && cbor != hcert {
+                            return Err(String::from("Payload doesn't match the CBOR data"));
+                        }
+                    }
+                    Ok(cose)
+                });
+                runner.check(Stage::Decode, expected.decode, ok_or_message(&cose));
+                cose.ok()
+            }
+            None => {
+                runner.skip(Stage::Decode, expected.decode);
+                None
+            }
+        };
+
+        // Signature
+        match (&cose, &self.context.certificate) {
+            (Some(cose), Some(certificate)) => {
+                runner.check(Stage::Verify, expected.verify, verify(cose, certificate))
+            }
+            _ => runner.skip(Stage::Verify, expected.verify),
+        }
+
+        // CWT claims and the health certificate
+        let claims = match cose
+            .as_ref()
+            .map(|cose| payload_of(cose).and_then(CwtClaims::try_from))
+        {
+            Some(claims) => {
+                let claims = claims.and_then(|claims| {
+                    claims.health_certificate()?;
+                    Ok(claims)
+                });
+                runner.check(
+                    Stage::ValidObject,
+                    expected.valid_object,
+                    ok_or_message(&claims),
+                );
+                claims.ok()
+            }
+            None => {
+                runner.skip(Stage::ValidObject, expected.valid_object);
+                None
+            }
+        };
+
+        match (&claims, &self.json) {
+            (Some(claims), Some(json)) => {
+                let decoded: Result<JsonValue, String> = claims
+                    .hcert
+                    .deserialized()
+                    .map_err(|e| format!("Health certificate isn't JSON: {}", e));
+                let matches = decoded.and_then(|decoded| {
+                    if &decoded != json {
+                        return Err(String::from("Health certificate doesn't match the JSON"));
+                    }
+                    Ok(())
+                });
+                runner.check(Stage::ValidJson, expected.valid_json, matches)
+            }
+            _ => runner.skip(Stage::ValidJson, expected.valid_json),
+        }
+
+        match &claims {
+            Some(claims) => {
+                let violations = validate_schema(&claims.hcert);
+                let valid = match violations.first() {
+                    None => Ok(()),
+                    Some(violation) => Err(violation.to_string()),
+                };
+                runner.check(Stage::SchemaValidation, expected.schema_validation, valid)
+            }
+            None => runner.skip(Stage::SchemaValidation, expected.schema_validation),
+        }
+
+        match (&claims, &self.context.validation_clock) {
+            (Some(claims), Some(clock)) => {
+                let validity = parse_date_time(clock).and_then(|clock| {
+                    match check_validity(claims, clock.timestamp(), 0) {
+                        Validity::Valid => Ok(()),
+                        validity => Err(format!("{:?} at {}", validity, clock)),
+                    }
+                });
+                runner.check(Stage::ExpirationCheck, expected.expiration_check, validity)
+            }
+            _ => runner.skip(Stage::ExpirationCheck, expected.expiration_check),
+        }
+
+        runner.skip(Stage::KeyUsage, expected.key_usage);
+        runner.skip(Stage::Encode, expected.encode);
+
+        runner.results
+    }
+}
+
+///
+/// Collects the results of the stages with an expectation
+///
+struct Runner {
+    results: Vec<StageResult>,
+}
+
+impl Runner {
+    fn check<T>(&mut self, stage: Stage, expected: Option<bool>, outcome: Result<T, String>) {
+        if let Some(expected) = expected {
+            self.results.push(StageResult {
+                stage,
+                expected,
+                actual: Some(outcome.is_ok()),
+                message: outcome.err(),
+            });
+        }
+    }
+
+    fn skip(&mut self, stage: Stage, expected: Option<bool>) {
+        if let Some(expected) = expected {
+            self.results.push(StageResult {
+                stage,
+                expected,
+                actual: None,
+                message: None,
+            });
+        }
+    }
+}
+
+///
+/// Verify the signature with the DSC of the test context
+///
+fn verify(cose: &CoseSingleSigned, certificate: &str) -> Result<(), String> {
+    let mut trust_store = TrustStore::new();
+    trust_store.add_certificate(&cose.kid().unwrap_or_default(), certificate);
+    let mut options = ParseOptions::new();
+    options.set_trust_store(&trust_store);
+
+    Ok(verify_signature_with_keys(
+        cose,
+        &options,
+        &mut KeyCache::new(),
+        &mut Vec::new(),
+    )?)
+}
+
+///
+/// The outcome of a stage without its value
+///
+fn ok_or_message<T>(outcome: &Result<T, String>) -> Result<(), String> {
+    match outcome {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.clone()),
+    }
+}
+
+///
+/// The decoded data equals the data of the test case, if the test case has it
+///
+fn equals(decoded: &[u8], expected: Option<&Vec<u8>>, name: &str) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != decoded => Err(format!("Doesn't match the {}", name)),
+        _ => Ok(()),
+    }
+}
+
+///
+/// CBOR data as JSON, none if it can't be converted
+///
+fn json(value: &Value) -> Option<JsonValue> {
+    value.deserialized().ok()
+}
+
+///
+/// The cbor decoded payload of a COSE message
+///
+fn payload_of(cose: &CoseSingleSigned) -> Result<&Value, String> {
+    cose.payload()
+        .ok_or_else(|| String::from("Payload is not cbor encoded"))
+}
+
+///
+/// Decode a hex string
+///
+fn hex_decode(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 {
+        return Err(String::from("Hex string has an odd length"));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("Invalid hex at {}", i))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcert::HealthCertificate;
+    use crate::test_data::{FixtureKind, TestDataGenerator, HEALTH_CERTIFICATE_JSON};
+
+    const NOW: f64 = 1622316073.0;
+
+    fn hex_encode(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// A test case with all stages of a generated DCC
+    fn test_case(kind: FixtureKind, expected: &str) -> TestCase {
+        let json: JsonValue = serde_json::from_str(HEALTH_CERTIFICATE_JSON).unwrap();
+        let health_certificate: HealthCertificate = serde_json::from_value(json.clone()).unwrap();
+        let fixture = TestDataGenerator::new("NL", NOW)
+            .unwrap()
+            .fixture(kind, &health_certificate)
+            .unwrap();
+
+        let base45 = fixture.dcc.strip_prefix("HC1:").unwrap().to_string();
+        let compressed = base45_decode(&base45).unwrap();
+        let cose = decompress(&compressed).unwrap();
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(decode_cose(&cose).unwrap().payload().unwrap(), &mut cbor)
+            .unwrap();
+
+        TestCase {
+            json: Some(json),
+            cbor: Some(hex_encode(&cbor)),
+            cose: Some(hex_encode(&cose)),
+            compressed: Some(hex_encode(&compressed)),
+            base45: Some(base45),
+            prefix: Some(fixture.dcc.clone()),
+            qr_code: None,
+            context: TestContext {
+                certificate: Some(fixture.certificate),
+                validation_clock: Some(String::from("2021-05-29T19:21:13Z")),
+                ..TestContext::default()
+            },
+            expected: serde_json::from_str(expected).unwrap(),
+        }
+    }
+
+    #[test]
+    fn it_reads_a_test_case() {
+        let test_case = TestCase::from_json(
+            r#"{"PREFIX": "HC1:ABC", "TESTCTX": {"VERSION": 1, "VALIDATIONCLOCK": "2021-05-29T19:21:13Z"},
+            "EXPECTEDRESULTS": {"EXPECTEDUNPREFIX": true, "EXPECTEDB45DECODE": false}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(test_case.prefix, Some(String::from("HC1:ABC")));
+        assert_eq!(test_case.context.version, Some(1));
+        assert_eq!(test_case.expected.unprefix, Some(true));
+        assert_eq!(test_case.expected.verify, None);
+        assert!(TestCase::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn a_valid_dcc_passes_all_stages() {
+        let results = test_case(
+            FixtureKind::Valid,
+            r#"{"EXPECTEDUNPREFIX": true, "EXPECTEDB45DECODE": true, "EXPECTEDCOMPRESSION": true,
+            "EXPECTEDDECODE": true, "EXPECTEDVERIFY": true, "EXPECTEDVALIDOBJECT": true,
+            "EXPECTEDVALIDJSON": true, "EXPECTEDSCHEMAVALIDATION": true,
+            "EXPECTEDEXPIRATIONCHECK": true, "EXPECTEDKEYUSAGE": true}"#,
+        )
+        .run();
+
+        assert_eq!(results.len(), 10);
+        assert!(
+            results.iter().all(|result| !result.failed()),
+            "{:?}",
+            results
+        );
+
+        // Key usage isn't supported, it's neither passed nor failed
+        let skipped: Vec<&StageResult> = results.iter().filter(|result| result.skipped()).collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].stage, Stage::KeyUsage);
+        assert!(!skipped[0].passed());
+    }
+
+    #[test]
+    fn failing_stages_are_reported() {
+        let results = test_case(
+            FixtureKind::Tampered,
+            r#"{"EXPECTEDVERIFY": true, "EXPECTEDEXPIRATIONCHECK": true}"#,
+        )
+        .run();
+
+        assert_eq!(results[0].stage, Stage::Verify);
+        assert!(results[0].failed());
+        assert!(results[1].passed());
+    }
+
+    #[test]
+    fn expected_failures_pass() {
+        let mut test_case = test_case(
+            FixtureKind::Expired,
+            r#"{"EXPECTEDEXPIRATIONCHECK": false, "EXPECTEDVERIFY": true, "EXPECTEDB45DECODE": false}"#,
+        );
+        test_case.base45 = Some(String::from("not base45!"));
+        test_case.compressed = None;
+
+        let results = test_case.run();
+        assert!(
+            results.iter().all(|result| result.passed()),
+            "{:?}",
+            results
+        );
+    }
+}
@@ -3,6 +3,9 @@ use std::convert::{TryFrom, TryInto};
 
 type Headers = Vec<(Value, Value)>;
 
+/// CBOR tag of a COSE_Sign1 message
+const COSE_SIGN1_TAG: u64 = 18;
+
 ///
 /// A Single Signed Cose Message
 ///
@@ -14,6 +17,7 @@ pub struct CoseSingleSigned {
     protected_headers: ProtectedHeaders,
     unprotected_headers: Headers,
     payload: Payload,
+    detached: bool,
     signature: Vec<u8>,
 }
 
@@ -26,18 +30,61 @@ struct ProtectedHeaders {
 }
 
 ///
-/// Payload of cose message
+/// Payload of cose message, the data is none when the
+/// raw bytes are not cbor encoded
 ///
 #[derive(PartialEq)]
 struct Payload {
     raw: Vec<u8>,
-    data: Value,
+    data: Option<Value>,
 }
 
 impl CoseSingleSigned {
-    /// Get a reference to the payload
-    pub fn payload(&self) -> &Value {
-        &self.payload.data
+    /// An unsigned message with the alg and kid in the protected headers
+    pub fn new(alg: i128, kid: &[u8], payload: &Value) -> Result<CoseSingleSigned, String> {
+        let protected_headers = vec![
+            (Value::from(Header::Alg.label()), Value::from(alg)),
+            (Value::from(Header::Kid.label()), Value::from(kid.to_vec())),
+        ];
+
+        Ok(CoseSingleSigned {
+            protected_headers: ProtectedHeaders {
+                raw: cbor_encode(&Value::Map(protected_headers.clone()))?,
+                data: protected_headers,
+            },
+            unprotected_headers: Vec::new(),
+            payload: Payload {
+                raw: cbor_encode(payload)?,
+                data: Some(payload.clone()),
+            },
+            detached: false,
+            signature: Vec::new(),
+        })
+    }
+
+    /// Get a reference to the cbor decoded payload, none when the payload is
+    /// detached and not yet attached or the content is not cbor encoded
+    pub fn payload(&self) -> Option<&Value> {
+        self.payload.data.as_ref()
+    }
+
+    /// The payload is not part of the message, but has to be provided separately
+    pub fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    /// Provide the content of a detached payload, the signature is over the raw
+    /// bytes and the content only has to be cbor encoded to read the claims
+    pub fn attach_payload(&mut self, content: &[u8]) -> Result<(), String> {
+        if !self.detached {
+            return Err(String::from("Payload is not detached"));
+        }
+
+        self.payload = Payload {
+            raw: content.to_vec(),
+            data: ciborium::de::from_reader(content).ok(),
+        };
+        Ok(())
     }
 
     /// Get a reference to the signature
@@ -53,13 +100,7 @@ impl CoseSingleSigned {
             kid = header(&self.unprotected_headers, Header::Kid)
         }
 
-        let kid_bytes = match kid {
-            Some(kid) => match kid.as_bytes() {
-                Some(b) => b,
-                None => return None,
-            },
-            None => return None,
-        };
+        let kid_bytes = kid?.as_bytes()?;
 
         Some(base64::encode(kid_bytes))
     }
@@ -72,18 +113,60 @@ impl CoseSingleSigned {
             alg = header(&self.unprotected_headers, Header::Alg);
         }
 
-        alg?;
-
-        let int: i128 = alg.unwrap().as_integer().unwrap().try_into().unwrap();
+        let int: i128 = alg?.as_integer()?.into();
         Some(int)
     }
 
-    /// The value that has to be signed
-    pub fn to_be_signed(&self) -> [Value; 4] {
+    /// Get the certificate chain embedded in the x5chain header, the first
+    /// certificate is the one used for signing. Prefer the protected headers.
+    pub fn x5chain(&self) -> Vec<Vec<u8>> {
+        let mut x5chain = header(&self.protected_headers.data, Header::X5Chain);
+
+        if x5chain.is_none() {
+            x5chain = header(&self.unprotected_headers, Header::X5Chain);
+        }
+
+        match x5chain {
+            Some(Value::Bytes(cert)) => vec![cert.clone()],
+            Some(Value::Array(certs)) => certs
+                .iter()
+                .filter_map(|cert| cert.as_bytes().cloned())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Set the signature over the Sig_structure
+    pub fn set_signature(&mut self, signature: Vec<u8>) {
+        self.signature = signature;
+    }
+
+    /// The message as tagged COSE_Sign1 structure, cbor encoded
+    pub fn to_cbor(&self) -> Result<Vec<u8>, String> {
+        let payload = if self.detached {
+            Value::Null
+        } else {
+            Value::from(self.payload.raw.clone())
+        };
+
+        cbor_encode(&Value::Tag(
+            COSE_SIGN1_TAG,
+            Box::new(Value::Array(vec![
+                Value::from(self.protected_headers.raw.clone()),
+                Value::Map(self.unprotected_headers.clone()),
+                payload,
+                Value::from(self.signature.clone()),
+            ])),
+        ))
+    }
+
+    /// The value that has to be signed (Sig_structure), the external_aad
+    /// is additional data supplied by the application, empty for a DCC
+    pub fn to_be_signed(&self, external_aad: &[u8]) -> [Value; 4] {
         [
             Value::from("Signature1"),
             Value::from(self.protected_headers.raw.clone()),
-            Value::Bytes(vec![]),
+            Value::from(external_aad.to_vec()),
             Value::from(self.payload.raw.clone()),
         ]
     }
@@ -96,8 +179,16 @@ impl TryFrom<[Value; 4]> for CoseSingleSigned {
         // convert protected headers
         let protected_headers: ProtectedHeaders = value[0].clone().try_into()?;
 
-        // convert payload
-        let payload: Payload = value[2].clone().try_into()?;
+        // convert payload, a nil payload means detached content
+        let detached = value[2].is_null();
+        let payload: Payload = if detached {
+            Payload {
+                raw: Vec::new(),
+                data: None,
+            }
+        } else {
+            value[2].clone().try_into()?
+        };
 
         // convert unprotected headers
         let unprotected_headers: Headers = match value[1].as_map() {
@@ -115,6 +206,7 @@ impl TryFrom<[Value; 4]> for CoseSingleSigned {
             protected_headers,
             unprotected_headers,
             payload,
+            detached,
             signature,
         })
     }
@@ -132,7 +224,7 @@ impl TryFrom<Value> for Payload {
         match ciborium::de::from_reader(&bytes[..]) {
             Ok(value) => Ok(Payload {
                 raw: bytes.clone(),
-                data: value,
+                data: Some(value),
             }),
             Err(_e) => Err(String::from("Payload is not cbor encoded")),
         }
@@ -175,6 +267,7 @@ impl TryFrom<Value> for ProtectedHeaders {
 enum Header {
     Alg,
     Kid,
+    X5Chain,
 }
 
 impl Header {
@@ -183,6 +276,7 @@ impl Header {
         match *self {
             Header::Alg => 1,
             Header::Kid => 4,
+            Header::X5Chain => 33,
         }
     }
 }
@@ -197,7 +291,19 @@ fn header(headers: &[(Value, Value)], header_label: Header) -> Option<&Value> {
         .map(|header| &header.1)
 }
 
+///
+/// Encode a value to cbor bytes
+///
+fn cbor_encode(value: &Value) -> Result<Vec<u8>, String> {
+    let mut encoded: Vec<u8> = Vec::new();
+    match ciborium::ser::into_writer(value, &mut encoded) {
+        Ok(()) => Ok(encoded),
+        Err(e) => Err(format!("Error on cbor encoding: {}", e)),
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_conversion)]
 mod tests {
     use super::*;
     use ciborium::value::Value;
@@ -226,7 +332,7 @@ mod tests {
     fn payload_raw_data() {
         let value: Vec<u8> = cbor_encode(&"test");
         let payload = Payload::try_from(Value::from(&value[..])).unwrap();
-        assert_eq!(payload.data, Value::from("test"))
+        assert_eq!(payload.data, Some(Value::from("test")))
     }
 
     #[test]
@@ -278,11 +384,87 @@ mod tests {
             unprotected_headers: vec![(Value::from(1), Value::from(10))].try_into().unwrap(),
             payload: Payload {
                 raw: vec![],
-                data: Value::Null,
+                data: None,
             },
+            detached: false,
             signature: vec![],
         };
 
         assert_eq!(cose.alg(), Some(-7));
     }
+
+    #[test]
+    fn x5chain_single_certificate() {
+        let mut values = test_values();
+        values[1] = Value::Map(vec![(Value::from(33), Value::from(vec![1u8, 2, 3]))]);
+        let cose = CoseSingleSigned::try_from(values).unwrap();
+
+        assert_eq!(cose.x5chain(), vec![vec![1u8, 2, 3]]);
+    }
+
+    #[test]
+    fn x5chain_multiple_certificates() {
+        let mut values = test_values();
+        values[1] = Value::Map(vec![(
+            Value::from(33),
+            Value::Array(vec![Value::from(vec![1u8]), Value::from(vec![2u8])]),
+        )]);
+        let cose = CoseSingleSigned::try_from(values).unwrap();
+
+        assert_eq!(cose.x5chain(), vec![vec![1u8], vec![2u8]]);
+    }
+
+    #[test]
+    fn to_be_signed_contains_external_aad() {
+        let cose = CoseSingleSigned::try_from(test_values()).unwrap();
+
+        assert_eq!(cose.to_be_signed(&[])[2], Value::Bytes(vec![]));
+        assert_eq!(cose.to_be_signed(&[1, 2])[2], Value::Bytes(vec![1, 2]));
+    }
+
+    #[test]
+    fn x5chain_is_empty_when_missing() {
+        let cose = CoseSingleSigned::try_from(test_values()).unwrap();
+        assert!(cose.x5chain().is_empty());
+    }
+
+    #[test]
+    fn nil_payload_is_detached() {
+        let mut values = test_values();
+        values[2] = Value::Null;
+        let cose = CoseSingleSigned::try_from(values).unwrap();
+
+        assert!(cose.is_detached());
+        assert_eq!(cose.payload(), None);
+    }
+
+    #[test]
+    fn attach_detached_payload() {
+        let mut values = test_values();
+        values[2] = Value::Null;
+        let mut cose = CoseSingleSigned::try_from(values).unwrap();
+        let content = cbor_encode("some_payload");
+
+        assert!(cose.attach_payload(&content).is_ok());
+        assert_eq!(cose.payload(), Some(&Value::from("some_payload")));
+        assert_eq!(cose.to_be_signed(&[])[3], Value::from(content));
+    }
+
+    #[test]
+    fn attach_detached_content_which_is_not_cbor() {
+        let mut values = test_values();
+        values[2] = Value::Null;
+        let mut cose = CoseSingleSigned::try_from(values).unwrap();
+        let content = vec![0xff, 0x00, 0x01];
+
+        assert!(cose.attach_payload(&content).is_ok());
+        assert_eq!(cose.payload(), None);
+        assert_eq!(cose.to_be_signed(&[])[3], Value::from(content));
+    }
+
+    #[test]
+    fn attach_payload_when_not_detached_is_error() {
+        let mut cose = CoseSingleSigned::try_from(test_values()).unwrap();
+        assert!(cose.attach_payload(&cbor_encode("some_payload")).is_err());
+    }
 }
@@ -0,0 +1,316 @@
+use crate::claims::CwtClaims;
+use crate::error::{DccError, ErrorCode};
+use crate::hcert::HealthCertificate;
+use crate::revocation::HashType;
+use crate::rules::RuleResult;
+use crate::schema::SchemaViolation;
+use crate::schema_version::SchemaVersion;
+use crate::status::{overall_status, Status};
+use crate::uvci::Uvci;
+use crate::validity::Validity;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+///
+/// A decoded and verified Digital Covid Certificate
+///
+/// The native counterpart of the ParsingResult, with the same
+/// fields but without javascript values, so it can be serialized.
+///
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecodedDcc {
+    /// Overall status computed from all steps
+    pub status: Status,
+
+    /// The algorithm used to sign the certificate
+    pub algorithm: i32,
+
+    /// Unique identifier for certificate issuer
+    pub kid: String,
+
+    /// Parse successful
+    pub successful: bool,
+
+    /// Error message when parsing or verifying the signature fails
+    pub error: String,
+
+    /// Stable code of the error, None when there is no error
+    pub error_code: ErrorCode,
+
+    /// Error while decoding the data
+    pub decode_error: Option<DccError>,
+
+    /// Error while verifying the signature, validity or revocation
+    pub verification_error: Option<DccError>,
+
+    /// Data in the DCC
+    pub data: JsonValue,
+
+    /// Typed CWT claims in the DCC
+    pub claims: CwtClaims,
+
+    /// The health certificate according to the eu-dcc-schema
+    pub health_certificate: Option<HealthCertificate>,
+
+    /// The health certificate with codes replaced by their
+    /// display from the value sets
+    pub display: Option<JsonValue>,
+
+    /// The parsed certificate identifier
+    pub uvci: Option<Uvci>,
+
+    /// Schema version of the health certificate, e.g. 1.3.0
+    pub schema_version: Option<String>,
+
+    /// Deviations from the rules of the schema version
+    pub schema_deviations: Vec<String>,
+
+    /// Violations of the eu-dcc-schema, only when validation is enabled
+    pub schema_violations: Vec<SchemaViolation>,
+
+    /// signature valid
+    pub signature_valid: bool,
+
+    /// The certificate is found in a revocation list
+    pub revoked: bool,
+
+    /// Validity in time, expired or not yet valid
+    pub validity: Validity,
+
+    /// Results of the business rules, only when rules are set in the options
+    pub rule_results: Vec<RuleResult>,
+
+    /// Non fatal deviations from the specifications
+    pub warnings: Vec<String>,
+}
+
+/// A builder to construct a DecodedDcc
+pub struct DecodedDccBuilder {
+    pub successful: bool,
+    pub error: String,
+    pub error_code: ErrorCode,
+    pub decode_error: Option<DccError>,
+    pub verification_error: Option<DccError>,
+    pub data: JsonValue,
+    pub claims: CwtClaims,
+    pub health_certificate: Option<HealthCertificate>,
+    pub display: Option<JsonValue>,
+    pub uvci: Option<Uvci>,
+    pub schema_version: Option<String>,
+    pub schema_deviations: Vec<String>,
+    pub schema_violations: Vec<SchemaViolation>,
+    pub signature_valid: bool,
+    pub revoked: bool,
+    pub validity: Validity,
+    pub kid: String,
+    pub algorithm: i128,
+    pub rule_results: Vec<RuleResult>,
+    pub rules_evaluated: bool,
+    pub warnings: Vec<String>,
+}
+
+impl DecodedDccBuilder {
+    /// DecodedDccBuilder is used to build
+    /// a DecodedDcc. The DecodedDcc is by default
+    /// unsuccessful.
+    pub fn new() -> DecodedDccBuilder {
+        DecodedDccBuilder {
+            successful: false,
+            error: String::from(""),
+            error_code: ErrorCode::None,
+            decode_error: None,
+            verification_error: None,
+            data: JsonValue::Null,
+            claims: CwtClaims::default(),
+            health_certificate: None,
+            display: None,
+            uvci: None,
+            schema_version: None,
+            schema_deviations: Vec::new(),
+            schema_violations: Vec::new(),
+            signature_valid: false,
+            revoked: false,
+            validity: Validity::Unknown,
+            kid: String::from(""),
+            algorithm: 0,
+            rule_results: Vec::new(),
+            rules_evaluated: true,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Set the successful on the DecodedDcc to true
+    pub fn success(mut self) -> DecodedDccBuilder {
+        self.successful = true;
+        self
+    }
+
+    /// Parsing failure with an error
+    pub fn fail_with_error(mut self, error: &DccError) -> DecodedDccBuilder {
+        self.successful = false;
+        self.decode_error = Some(error.clone());
+        self.error(error)
+    }
+
+    /// Failure on verifying the signature
+    pub fn signature_error(mut self, error: &DccError) -> DecodedDccBuilder {
+        self.signature_valid = false;
+        self.verification_error(error)
+    }
+
+    /// Failure on verifying, e.g. when the certificate is expired
+    pub fn verification_error(mut self, error: &DccError) -> DecodedDccBuilder {
+        self.verification_error = Some(error.clone());
+        self.error(error)
+    }
+
+    /// Set the error message and code
+    fn error(mut self, error: &DccError) -> DecodedDccBuilder {
+        self.error = error.message.clone();
+        self.error_code = error.code;
+        self
+    }
+
+    /// Set data
+    pub fn data(mut self, data: JsonValue) -> DecodedDccBuilder {
+        self.data = data;
+        self
+    }
+
+    /// Set the typed CWT claims
+    pub fn claims(mut self, claims: CwtClaims) -> DecodedDccBuilder {
+        self.claims = claims;
+        self
+    }
+
+    /// Set the health certificate
+    pub fn health_certificate(
+        mut self,
+        health_certificate: HealthCertificate,
+    ) -> DecodedDccBuilder {
+        self.health_certificate = Some(health_certificate);
+        self
+    }
+
+    /// Set the display ready health certificate
+    pub fn display(mut self, display: JsonValue) -> DecodedDccBuilder {
+        self.display = Some(display);
+        self
+    }
+
+    /// Set the parsed certificate identifier
+    pub fn uvci(mut self, uvci: Uvci) -> DecodedDccBuilder {
+        self.uvci = Some(uvci);
+        self
+    }
+
+    /// Set the schema version and the deviations from its rules
+    pub fn schema_version(
+        mut self,
+        version: &SchemaVersion,
+        deviations: &[String],
+    ) -> DecodedDccBuilder {
+        self.schema_version = Some(version.to_string());
+        self.schema_deviations = deviations.to_vec();
+        self
+    }
+
+    /// Set the violations of the eu-dcc-schema
+    pub fn schema_violations(mut self, violations: &[SchemaViolation]) -> DecodedDccBuilder {
+        self.schema_violations = violations.to_vec();
+        self
+    }
+
+    /// Set when the signature is verified
+    pub fn signature_valid(mut self, valid: bool) -> DecodedDccBuilder {
+        self.signature_valid = valid;
+        self
+    }
+
+    /// The certificate is revoked, the hash of the type is
+    /// found in a revocation list
+    pub fn revoked(mut self, hash_type: HashType) -> DecodedDccBuilder {
+        self.revoked = true;
+        self.verification_error(&DccError::new(
+            ErrorCode::Revoked,
+            &format!("Certificate is revoked ({} hash)", hash_type),
+        ))
+    }
+
+    /// Set the validity in time
+    pub fn validity(mut self, validity: Validity) -> DecodedDccBuilder {
+        self.validity = validity;
+        self
+    }
+
+    /// Set the kid
+    pub fn kid(mut self, kid: &str) -> DecodedDccBuilder {
+        self.kid = kid.to_string();
+        self
+    }
+
+    /// Set the algorithm
+    pub fn alg(mut self, algorithm: i128) -> DecodedDccBuilder {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Set the results of the business rules
+    pub fn rule_results(mut self, rule_results: &[RuleResult]) -> DecodedDccBuilder {
+        self.rule_results = rule_results.to_vec();
+        self
+    }
+
+    /// The rules are set, but couldn't be evaluated
+    pub fn rules_not_evaluated(mut self) -> DecodedDccBuilder {
+        self.rules_evaluated = false;
+        self
+    }
+
+    /// Add warnings
+    pub fn warnings(mut self, warnings: &[String]) -> DecodedDccBuilder {
+        self.warnings.extend_from_slice(warnings);
+        self
+    }
+
+    /// Build DecodedDcc
+    pub fn build(self) -> DecodedDcc {
+        let rule_results = if self.rules_evaluated {
+            Some(self.rule_results.as_slice())
+        } else {
+            None
+        };
+        let status = overall_status(
+            self.error_code,
+            self.successful,
+            self.health_certificate.is_some(),
+            self.signature_valid,
+            self.validity,
+            rule_results,
+        );
+
+        DecodedDcc {
+            status,
+            successful: self.successful,
+            error: self.error,
+            error_code: self.error_code,
+            decode_error: self.decode_error,
+            verification_error: self.verification_error,
+            data: self.data,
+            claims: self.claims,
+            health_certificate: self.health_certificate,
+            display: self.display,
+            uvci: self.uvci,
+            schema_version: self.schema_version,
+            schema_deviations: self.schema_deviations,
+            schema_violations: self.schema_violations,
+            signature_valid: self.signature_valid,
+            revoked: self.revoked,
+            validity: self.validity,
+            kid: self.kid,
+            algorithm: self.algorithm as i32,
+            rule_results: self.rule_results,
+            warnings: self.warnings,
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+///
+/// Stable codes of the errors while parsing and verifying a DCC
+///
+/// The numbers and names don't change between releases, so they can be
+/// used to show a localized message. The codes below 10 are parse errors,
+/// 10 up to 20 signature errors and from 20 the certificate isn't accepted.
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// No error
+    None = 0,
+    /// The data is not base45 encoded
+    Base45 = 1,
+    /// The data can't be decompressed with zlib
+    Zlib = 2,
+    /// The data is not a cbor array
+    Cbor = 3,
+    /// The cbor array is not a COSE_Sign1 message
+    CoseStructure = 4,
+    /// The detached payload is missing or invalid
+    DetachedPayload = 5,
+    /// The payload can't be converted to JSON
+    Payload = 6,
+    /// No certificate known for the kid
+    UnknownKid = 10,
+    /// The certificate of the issuer can't be read
+    #[serde(rename = "bad-cert")]
+    BadCertificate = 11,
+    /// The public key of the certificate can't be read
+    BadKey = 12,
+    /// The algorithm is not supported or doesn't match the key
+    UnsupportedAlgorithm = 13,
+    /// The signature is invalid
+    BadSignature = 14,
+    /// The certificate embedded in the DCC is not trusted
+    #[serde(rename = "untrusted-cert")]
+    UntrustedCertificate = 15,
+    /// The expiration is in the past
+    Expired = 20,
+    /// The DCC is issued in the future
+    NotYetValid = 21,
+    /// The DCC is in a revocation list
+    Revoked = 22,
+    /// The certificate of the issuer may not sign this type of certificate
+    WrongCertificateType = 23,
+    /// The country of the certificate of the issuer doesn't match the issuer claim
+    WrongCountry = 24,
+}
+
+impl ErrorCode {
+    /// Name of the code, e.g. unknown-kid
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ErrorCode::None => "none",
+            ErrorCode::Base45 => "base45",
+            ErrorCode::Zlib => "zlib",
+            ErrorCode::Cbor => "cbor",
+            ErrorCode::CoseStructure => "cose-structure",
+            ErrorCode::DetachedPayload => "detached-payload",
+            ErrorCode::Payload => "payload",
+            ErrorCode::UnknownKid => "unknown-kid",
+            ErrorCode::BadCertificate => "bad-cert",
+            ErrorCode::BadKey => "bad-key",
+            ErrorCode::UnsupportedAlgorithm => "unsupported-algorithm",
+            ErrorCode::BadSignature => "bad-signature",
+            ErrorCode::UntrustedCertificate => "untrusted-cert",
+            ErrorCode::Expired => "expired",
+            ErrorCode::NotYetValid => "not-yet-valid",
+            ErrorCode::Revoked => "revoked",
+            ErrorCode::WrongCertificateType => "wrong-certificate-type",
+            ErrorCode::WrongCountry => "wrong-country",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+///
+/// An error with a stable code and a human readable message
+///
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DccError {
+    /// Stable code of the error
+    pub code: ErrorCode,
+    /// Human readable message
+    pub message: String,
+}
+
+impl DccError {
+    pub fn new(code: ErrorCode, message: &str) -> DccError {
+        DccError {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// The error with a message prefixed by the context
+    pub fn context(self, context: &str) -> DccError {
+        DccError {
+            message: format!("{}: {}", context, self.message),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for DccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<DccError> for String {
+    fn from(error: DccError) -> Self {
+        error.message
+    }
+}
+
+/// Attach a code to a plain error message
+pub(crate) trait WithCode<T> {
+    fn code(self, code: ErrorCode) -> Result<T, DccError>;
+}
+
+impl<T> WithCode<T> for Result<T, String> {
+    fn code(self, code: ErrorCode) -> Result<T, DccError> {
+        self.map_err(|message| DccError { code, message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        assert_eq!(ErrorCode::UnknownKid as u32, 10);
+        assert_eq!(ErrorCode::UnknownKid.name(), "unknown-kid");
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::BadSignature).unwrap(),
+            r#""bad-signature""#
+        );
+    }
+
+    #[test]
+    fn context_is_prefixed() {
+        let error = DccError::new(ErrorCode::Zlib, "invalid data").context("Couldn't parse");
+
+        assert_eq!(error.code, ErrorCode::Zlib);
+        assert_eq!(String::from(error), "Couldn't parse: invalid data");
+    }
+}
@@ -0,0 +1,276 @@
+use ciborium::value::Value;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use tsify::Tsify;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+///
+/// Health certificate according to the eu-dcc-schema
+///
+/// https://github.com/ehn-dcc-development/eu-dcc-schema
+///
+/// A DCC contains exactly one of the vaccination,
+/// test or recovery groups.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct HealthCertificate {
+    /// Schema version
+    pub ver: String,
+
+    /// Person name
+    pub nam: PersonName,
+
+    /// Date of birth
+    pub dob: String,
+
+    /// Vaccination group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<Vec<VaccinationEntry>>,
+
+    /// Test group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub t: Option<Vec<TestEntry>>,
+
+    /// Recovery group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r: Option<Vec<RecoveryEntry>>,
+}
+
+///
+/// Name of the holder
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct PersonName {
+    /// Surname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#fn: Option<String>,
+
+    /// Standardised surname
+    pub fnt: String,
+
+    /// Forename
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gn: Option<String>,
+
+    /// Standardised forename
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gnt: Option<String>,
+}
+
+///
+/// Vaccination entry
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct VaccinationEntry {
+    /// Disease or agent targeted
+    pub tg: String,
+
+    /// Vaccine or prophylaxis
+    pub vp: String,
+
+    /// Vaccine medicinal product
+    pub mp: String,
+
+    /// Marketing authorization holder or manufacturer
+    pub ma: String,
+
+    /// Dose number
+    pub dn: u32,
+
+    /// Total series of doses
+    pub sd: u32,
+
+    /// Date of vaccination
+    pub dt: String,
+
+    /// Country of vaccination
+    pub co: String,
+
+    /// Certificate issuer
+    pub is: String,
+
+    /// Unique certificate identifier
+    pub ci: String,
+}
+
+///
+/// Test entry
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct TestEntry {
+    /// Disease or agent targeted
+    pub tg: String,
+
+    /// Type of test
+    pub tt: String,
+
+    /// Test name, for a NAA test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nm: Option<String>,
+
+    /// Test device identifier, for a rapid antigen test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ma: Option<String>,
+
+    /// Date and time of the test sample collection
+    pub sc: String,
+
+    /// Date and time of the test result, until schema version 1.3.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dr: Option<String>,
+
+    /// Test result
+    pub tr: String,
+
+    /// Testing centre
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tc: Option<String>,
+
+    /// Country of test
+    pub co: String,
+
+    /// Certificate issuer
+    pub is: String,
+
+    /// Unique certificate identifier
+    pub ci: String,
+}
+
+///
+/// Recovery entry
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct RecoveryEntry {
+    /// Disease or agent from which the holder recovered
+    pub tg: String,
+
+    /// Date of the first positive NAA test result
+    pub fr: String,
+
+    /// Country of test
+    pub co: String,
+
+    /// Certificate issuer
+    pub is: String,
+
+    /// Certificate valid from
+    pub df: String,
+
+    /// Certificate valid until
+    pub du: String,
+
+    /// Unique certificate identifier
+    pub ci: String,
+}
+
+impl HealthCertificate {
+    /// The unique certificate identifier (ci) of the first
+    /// vaccination, test or recovery entry
+    pub fn certificate_identifier(&self) -> Option<&str> {
+        let v = self.v.iter().flatten().map(|entry| entry.ci.as_str());
+        let t = self.t.iter().flatten().map(|entry| entry.ci.as_str());
+        let r = self.r.iter().flatten().map(|entry| entry.ci.as_str());
+        v.chain(t).chain(r).next()
+    }
+
+    /// The health certificate as CBOR value, as part of the CWT claims
+    pub fn to_cbor(&self) -> Result<Value, String> {
+        match Value::serialized(self) {
+            Ok(hcert) => Ok(hcert),
+            Err(e) => Err(format!("Couldn't encode health certificate: {}", e)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for HealthCertificate {
+    type Error = String;
+
+    fn try_from(hcert: &Value) -> Result<Self, Self::Error> {
+        match hcert.deserialized() {
+            Ok(health_certificate) => Ok(health_certificate),
+            Err(e) => Err(format!(
+                "Health certificate doesn't match the schema: {}",
+                e
+            )),
+        }
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// A HealthCertificate as javascript object
+    #[wasm_bindgen(typescript_type = "HealthCertificate | undefined")]
+    #[derive(Clone)]
+    pub type JsHealthCertificate;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claims::CwtClaims;
+    use crate::read_dcc::read_dcc;
+
+    const TEST_DCC: &str = "6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1";
+
+    fn test_health_certificate() -> HealthCertificate {
+        let claims = CwtClaims::try_from(read_dcc(TEST_DCC).unwrap().payload().unwrap()).unwrap();
+        HealthCertificate::try_from(&claims.hcert).unwrap()
+    }
+
+    #[test]
+    fn it_reads_the_person() {
+        let health_certificate = test_health_certificate();
+
+        assert_eq!(health_certificate.ver, "1.0.0");
+        assert_eq!(health_certificate.dob, "1964-08-12");
+        assert_eq!(
+            health_certificate.nam.r#fn,
+            Some(String::from("Mustermann"))
+        );
+        assert_eq!(health_certificate.nam.fnt, "MUSTERMANN");
+    }
+
+    #[test]
+    fn it_reads_a_recovery() {
+        let health_certificate = test_health_certificate();
+        let recovery = &health_certificate.r.as_ref().unwrap()[0];
+
+        assert_eq!(recovery.tg, "840539006");
+        assert_eq!(recovery.fr, "2021-01-10");
+        assert_eq!(recovery.ci, "URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W");
+        assert_eq!(health_certificate.v, None);
+        assert_eq!(health_certificate.t, None);
+        assert_eq!(
+            health_certificate.certificate_identifier(),
+            Some("URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W")
+        );
+    }
+
+    #[test]
+    fn it_reads_a_vaccination() {
+        let hcert: Value = serde_json::from_str(
+            r#"{"ver": "1.3.0", "nam": {"fnt": "DOE"}, "dob": "1990",
+            "v": [{"tg": "840539006", "vp": "1119349007", "mp": "EU/1/20/1528",
+            "ma": "ORG-100030215", "dn": 2, "sd": 2, "dt": "2021-06-01",
+            "co": "NL", "is": "Ministry", "ci": "URN:UVCI:01:NL:123"}]}"#,
+        )
+        .unwrap();
+        let vaccination = &HealthCertificate::try_from(&hcert).unwrap().v.unwrap()[0];
+
+        assert_eq!(vaccination.mp, "EU/1/20/1528");
+        assert_eq!(vaccination.dn, 2);
+    }
+
+    #[test]
+    fn typescript_definition_has_optional_groups() {
+        assert!(HealthCertificate::DECL.starts_with("export interface HealthCertificate {"));
+        assert!(HealthCertificate::DECL.contains("v?: VaccinationEntry[];"));
+        assert!(PersonName::DECL.contains("fn?: string;"));
+    }
+
+    #[test]
+    fn missing_fields_are_error() {
+        let hcert: Value = serde_json::from_str(r#"{"ver": "1.3.0"}"#).unwrap();
+        assert!(HealthCertificate::try_from(&hcert).is_err())
+    }
+}
@@ -0,0 +1,269 @@
+use crate::claims::CwtClaims;
+use crate::cose::CoseSingleSigned;
+use crate::from_js_value;
+use crate::hcert::HealthCertificate;
+use ecdsa::signature::{RandomizedSigner, SignatureEncoding, Signer};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rsa::pkcs8::DecodePrivateKey;
+use sha2::Sha256;
+use std::io::Write;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+///
+/// Private key to sign a DCC with
+///
+/// https://github.com/ehn-dcc-development/hcert-spec/blob/main/hcert_spec.md#332-signature-algorithm
+///
+enum SigningKey {
+    ES256(p256::ecdsa::SigningKey),
+    PS256(Box<rsa::pss::BlindedSigningKey<Sha256>>),
+}
+
+impl SigningKey {
+    /// COSE algorithm identifier
+    fn alg(&self) -> i128 {
+        match *self {
+            SigningKey::ES256(_) => -7,
+            SigningKey::PS256(_) => -37,
+        }
+    }
+
+    /// Sign the data, an ECDSA signature is the concatenation of r and s
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            SigningKey::ES256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(data);
+                signature.to_vec()
+            }
+            SigningKey::PS256(key) => key.sign_with_rng(&mut rsa::rand_core::OsRng, data).to_vec(),
+        }
+    }
+}
+
+///
+/// Issuer of Digital Covid Certificates
+///
+/// Signs health certificates with the private key of a document signer
+/// certificate (DSC). The kid is the base64 encoded key identifier of the
+/// DSC: the first 8 bytes of the SHA-256 hash of the DER encoded certificate.
+///
+#[wasm_bindgen]
+pub struct Issuer {
+    key: SigningKey,
+    kid: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Issuer {
+    /// An issuer signing with ES256, the private key is a PKCS#8 DER encoded P-256 key
+    pub fn es256(private_key: &[u8], kid: &str) -> Result<Issuer, String> {
+        let key = match p256::ecdsa::SigningKey::from_pkcs8_der(private_key) {
+            Ok(key) => key,
+            Err(e) => return Err(format!("Invalid P-256 private key: {}", e)),
+        };
+
+        Issuer::new(SigningKey::ES256(key), kid)
+    }
+
+    /// An issuer signing with PS256, the private key is a PKCS#8 DER encoded RSA key
+    pub fn ps256(private_key: &[u8], kid: &str) -> Result<Issuer, String> {
+        let key = match rsa::RsaPrivateKey::from_pkcs8_der(private_key) {
+            Ok(key) => key,
+            Err(e) => return Err(format!("Invalid RSA private key: {}", e)),
+        };
+
+        Issuer::new(
+            SigningKey::PS256(Box::new(rsa::pss::BlindedSigningKey::new(key))),
+            kid,
+        )
+    }
+
+    ///
+    /// Issue a DCC for the health certificate, the issuer is a country code
+    /// and issued_at and expiration are seconds since epoch
+    ///
+    #[wasm_bindgen(js_name = issue)]
+    pub fn issue_js(
+        &self,
+        health_certificate: JsValue,
+        issuer: &str,
+        issued_at: f64,
+        expiration: f64,
+    ) -> Result<String, String> {
+        let health_certificate: HealthCertificate = from_js_value(&health_certificate)?;
+        let claims = CwtClaims::new(
+            issuer,
+            issued_at as i64,
+            expiration as i64,
+            &health_certificate,
+        )?;
+
+        self.issue(&claims)
+    }
+}
+
+impl Issuer {
+    fn new(key: SigningKey, kid: &str) -> Result<Issuer, String> {
+        match base64::decode(kid) {
+            Ok(kid) => Ok(Issuer { key, kid }),
+            Err(e) => Err(format!("Error on base64 decoding kid: {}", e)),
+        }
+    }
+
+    /// Sign the claims and encode the COSE message as DCC
+    pub fn issue(&self, claims: &CwtClaims) -> Result<String, String> {
+        write_dcc(&self.sign(claims)?)
+    }
+
+    /// The claims in a COSE message signed by the issuer
+    pub fn sign(&self, claims: &CwtClaims) -> Result<CoseSingleSigned, String> {
+        let mut cose = CoseSingleSigned::new(self.key.alg(), &self.kid, &claims.to_cbor())?;
+
+        let mut to_be_signed: Vec<u8> = Vec::new();
+        if let Err(e) = ciborium::ser::into_writer(&cose.to_be_signed(&[]), &mut to_be_signed) {
+            return Err(format!("Error on cbor encoding: {}", e));
+        }
+
+        cose.set_signature(self.key.sign(&to_be_signed));
+        Ok(cose)
+    }
+}
+
+///
+/// Encode a COSE message as DCC, the reverse of read_dcc
+///
+pub fn write_dcc(cose: &CoseSingleSigned) -> Result<String, String> {
+    // Zlib compress
+    let mut compressor = ZlibEncoder::new(Vec::new(), Compression::best());
+    let compressed = match compressor
+        .write_all(&cose.to_cbor()?)
+        .and_then(|_| compressor.finish())
+    {
+        Ok(compressed) => compressed,
+        Err(e) => return Err(format!("Error on zlib compressing: {}", e)),
+    };
+
+    // Base45 encode and add HC1: prefix
+    Ok(format!("HC1:{}", base45::encode(&compressed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_options::ParseOptions;
+    use crate::read_dcc::read_dcc;
+    use crate::status::Status;
+    use crate::test_data::HEALTH_CERTIFICATE_JSON;
+    use crate::trust_store::TrustStore;
+    use crate::verifier::DccVerifier;
+    use ciborium::value::Value;
+    use ecdsa::signature::Verifier;
+    use std::convert::TryFrom;
+
+    /// PKCS#8 encoded P-256 private key
+    const P256_KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgQna3sTjlSM0MwH2+zk8FN3lXNN832GTs8slBquoABhKhRANCAAQ4imsyKKtiNr55F+oWJnXM4sozoP1b8gKimqe9YPMDsSFb0EAgC0/iO/7LPJL/hq/Un9f3xCDycP9CKDpU6/Kg";
+
+    /// PKCS#8 encoded 2048 bit RSA private key
+    const RSA_KEY: &str = "MIIEvgIBADANBgkqhkiG9w0BAQEFAASCBKgwggSkAgEAAoIBAQCf57/eMSlG+dEL4DkOy2Ecs3KFuHmV4TR6mw93fOrO5CMhb2cOleIQAUso3pMalWyVoz06LDcncNjmb3pNcAQAsev4sery7qQ9lotPVmJ+xlwztfVXcM9SEduF4QfsGeNg/ORT+eYDYoIK4DB1aknkZqd7sSFfCZuOG7jDVmUBTCR44yNax9vBeViNBXj+oRHjC9cW0tr3oAtB3uvrFLa02YfXBBvBKDoNsIAtTqN859/DMdIk9C5tjWp1Diox9KQZF/uwiDQJhpLmD+2MPXhHXc2g85G1p/zCBggYfAh4wMUOQ61p+wkgZ5UkwlKBOjvScivE4H3TYU9s5DKmqWHpAgMBAAECggEABiv6Nle8aZ5jyKsPWLhV17c/YNTlcHkAPwk0GB8Mqw6YIoa7ug7j6k06eunYUKraxY3G47NoL5AF9yLvXATKSsKTQoaPdG/si+n/F4FUjOMYFPDddtIGVnsU935fgWfgZFJud6VKEPBg542OoUGId5XUugv0p0fmJp6AS53OiTW5ASSSLXHPVrhAz9crPegqORtvuepgNsFq8LKpxunqOlR+T42jIqmskvJp2p/bqoeCvmM819la1M27D4U4Gi7RFcQd1Na+5h2/j32082ctvmvqgRxSsWI1EB6i0T8eqffl9LZCTbQ845AaMIJxKmiYdUN6QK4Sn+gvKPCH5T7pgQKBgQDU6oe9rdpm2t8FN5ZZL74syJg8WK3eaP1hmOCz3Um1MfThDZGj3HWWcVx/sP5B/F0tNIUdEfWMnXYBDV7lz4UcRyhpH1a9YUNzRNS2kvb9ZDMQF7PsdDRQOe5tr4WGS0voDdS56xTmE7IvdHxF8cKnrzVpTbJpS2uxklyAhRQJyQKBgQDAQydLXESQ7onGciC7Z4OYt03sE5o4aDbozQMHXbnjRs4edT/LtkN9v1i6ZZJjYsT24apOPYgaFHMeLwgdBn+YW9vgzcHPOe7aE8TpPJBlXvndkKdzsczkaipvDiMZRNQn++hydkrTxmwMUAN9ENUOQbTwmXSw5WRHQnL3ICKnIQKBgQCqpxAFR8M1jVgTpmorMckLu/vI4or5KM1t3Eq93y+ku1W6zFVfMOROoK2y6vnfiZZlFSNcGXjmK47ZaQ4WxC28bIxHNYcgY0Tcrt6PN1wf3J/Q1i6v5rPHWadw4q3y5YcBh5RpL7KgdkjrIEI7DXsnS5dTv6KwInM+8ECUAhX9gQKBgELxXuYze0BHfjtVk6YM9dofwQb5EFKhnrTSZUKUVNwY1D3wr0Tb8oX/MQK9yQnH4zLfAjB55kDGbArPb3DDyXugSF/u5JOl5U+TYJWhW9mgBzopWtTCzMefW6UNdBUhTQmbAaWRIJ66UPa8iWpriPOM11aK6AGL7a2OKn93dflhAoGBAM4LikJMtdA0UCfJMzr2uWP0Rt9cLHS3/JT0LjSrIFDgjT9F72cE7dKLOpwr+nEND9htJvSUzWIfKcgYJtBt+Ef5JUZkT8tEAs6MlzvmCu9jPG+bnKww4LrpU15DVtHMr5hPA+DYrc9t7IzvOjmeji89oyvEkFa8rt1R/SSDNd/d";
+
+    /// Self signed certificate of RSA_KEY for NL
+    const RSA_CERTIFICATE: &str = "MIIC1DCCAbygAwIBAgIUOhDvIiHaxgLZGs7UmrFjT8aqH+swDQYJKoZIhvcNAQELBQAwJDELMAkGA1UEBhMCTkwxFTATBgNVBAMMDFRlc3QgRFNDIFJTQTAeFw0yMTAxMDEwMDAwMDBaFw00NjAxMDEwMDAwMDBaMCQxCzAJBgNVBAYTAk5MMRUwEwYDVQQDDAxUZXN0IERTQyBSU0EwggEiMA0GCSqGSIb3DQEBAQUAA4IBDwAwggEKAoIBAQCf57/eMSlG+dEL4DkOy2Ecs3KFuHmV4TR6mw93fOrO5CMhb2cOleIQAUso3pMalWyVoz06LDcncNjmb3pNcAQAsev4sery7qQ9lotPVmJ+xlwztfVXcM9SEduF4QfsGeNg/ORT+eYDYoIK4DB1aknkZqd7sSFfCZuOG7jDVmUBTCR44yNax9vBeViNBXj+oRHjC9cW0tr3oAtB3uvrFLa02YfXBBvBKDoNsIAtTqN859/DMdIk9C5tjWp1Diox9KQZF/uwiDQJhpLmD+2MPXhHXc2g85G1p/zCBggYfAh4wMUOQ61p+wkgZ5UkwlKBOjvScivE4H3TYU9s5DKmqWHpAgMBAAEwDQYJKoZIhvcNAQELBQADggEBADvPPLH4s9xccLteWOnX3s8GSb0OY6dhJCUrkdPf6MYMxzfl5rz3L8uiyfLXgHF7O7pMr2L4qAhXZCOjTAJmX/3JlS5L/rtEMQ9WBqEy3yCFurkO5c29fWluZZcypMxhIYSTZ+f2pOh0W9gAJF9/fwVMH2j4F4EPYXBP7hppgUvIH92NOsl84nHwYZWI393Oa7+m8GxZXjIOWCFU3OOsPxFquNdNXsDuPmQ8gOf97XByVl5moma/BwD8ADjcn2z7+sHJ22iHigASAriqVz0npsZCmBCFz3y/yCWj3bVU8/+dbvYXCzI6gjbTQ7GgUWZmh1GmvX6P9e6TaJPke59gOvk=";
+
+    fn claims() -> CwtClaims {
+        let hcert: HealthCertificate = serde_json::from_str(HEALTH_CERTIFICATE_JSON).unwrap();
+
+        CwtClaims::new("NL", 1622316073, 1643356073, &hcert).unwrap()
+    }
+
+    fn to_be_signed(cose: &CoseSingleSigned) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&cose.to_be_signed(&[]), &mut encoded).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn it_issues_a_readable_dcc() {
+        let issuer = Issuer::es256(&base64::decode(P256_KEY).unwrap(), "AQIDBAUGBwg=").unwrap();
+        let dcc = issuer.issue(&claims()).unwrap();
+        assert!(dcc.starts_with("HC1:"));
+
+        let cose = read_dcc(&dcc).unwrap();
+        assert_eq!(cose.alg(), Some(-7));
+        assert_eq!(cose.kid(), Some(String::from("AQIDBAUGBwg=")));
+        assert_eq!(
+            CwtClaims::try_from(cose.payload().unwrap()).unwrap(),
+            claims()
+        );
+    }
+
+    #[test]
+    fn es256_signature_is_valid() {
+        let key = base64::decode(P256_KEY).unwrap();
+        let cose = Issuer::es256(&key, "AQIDBAUGBwg=")
+            .unwrap()
+            .sign(&claims())
+            .unwrap();
+
+        let signing_key = p256::ecdsa::SigningKey::from_pkcs8_der(&key).unwrap();
+        let signature = p256::ecdsa::Signature::from_slice(cose.signature()).unwrap();
+        assert!(signing_key
+            .verifying_key()
+            .verify(&to_be_signed(&cose), &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn ps256_signature_is_valid() {
+        let key = base64::decode(RSA_KEY).unwrap();
+        let dcc = Issuer::ps256(&key, "AQIDBAUGBwg=")
+            .unwrap()
+            .issue(&claims())
+            .unwrap();
+
+        let cose = read_dcc(&dcc).unwrap();
+        assert_eq!(cose.alg(), Some(-37));
+
+        let public_key = rsa::RsaPrivateKey::from_pkcs8_der(&key)
+            .unwrap()
+            .to_public_key();
+        let signature = rsa::pss::Signature::try_from(cose.signature()).unwrap();
+        assert!(rsa::pss::VerifyingKey::<Sha256>::new(public_key)
+            .verify(&to_be_signed(&cose), &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn ps256_dcc_is_verified() {
+        let issuer = Issuer::ps256(&base64::decode(RSA_KEY).unwrap(), "AQIDBAUGBwg=").unwrap();
+        let dcc = issuer.issue(&claims()).unwrap();
+
+        let mut trust_store = TrustStore::new();
+        trust_store.add_certificate("AQIDBAUGBwg=", RSA_CERTIFICATE);
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+        options.set_now(1622316073.0);
+        let decoded = DccVerifier::with_options(options).verify(&dcc);
+
+        assert!(decoded.signature_valid);
+        assert_eq!(decoded.algorithm, -37);
+        assert_eq!(decoded.status, Status::Valid);
+    }
+
+    #[test]
+    fn invalid_keys_are_error() {
+        assert!(Issuer::es256(&[1, 2, 3], "AQIDBAUGBwg=").is_err());
+        assert!(Issuer::ps256(&base64::decode(P256_KEY).unwrap(), "AQIDBAUGBwg=").is_err());
+        assert!(Issuer::es256(&base64::decode(P256_KEY).unwrap(), "not base64!").is_err());
+    }
+
+    #[test]
+    fn it_is_a_tagged_cose_message() {
+        let issuer = Issuer::es256(&base64::decode(P256_KEY).unwrap(), "AQIDBAUGBwg=").unwrap();
+        let encoded = issuer.sign(&claims()).unwrap().to_cbor().unwrap();
+        let value: Value = ciborium::de::from_reader(&encoded[..]).unwrap();
+
+        assert!(matches!(value, Value::Tag(18, _)));
+    }
+}
@@ -1,18 +1,66 @@
+mod batch;
+mod bloom_filter;
 mod certificates;
+mod certlogic;
+mod claims;
+#[cfg(any(test, feature = "conformance"))]
+mod conformance;
 mod cose;
+mod decoded_dcc;
+mod error;
+mod hcert;
+mod issue;
+mod parse_options;
 mod parsing_result;
 mod read_dcc;
+mod revocation;
+mod rules;
+mod schema;
+mod schema_version;
+mod status;
+#[cfg(any(test, feature = "test-data"))]
+mod test_data;
+mod trust_store;
+mod uvci;
+mod validity;
+mod value_sets;
+mod verifier;
+mod verify;
 
-use crate::certificates::find_issuer_cert;
-use crate::cose::CoseSingleSigned;
-use crate::parsing_result::{ParsingResult, ParsingResultBuilder};
-use crate::read_dcc::read_dcc;
-use ciborium::value::Value;
-use p256::ecdsa;
-use p256::ecdsa::signature::*;
-use p256::ecdsa::VerifyingKey;
+pub use crate::batch::{verify_batch, BatchResult, BatchStatistics, JsBatchResult};
+pub use crate::bloom_filter::BloomFilter;
+pub use crate::certlogic::evaluate as evaluate_certlogic;
+pub use crate::claims::CwtClaims;
+#[cfg(any(test, feature = "conformance"))]
+pub use crate::conformance::{ExpectedResults, Stage, StageResult, TestCase, TestContext};
+pub use crate::cose::CoseSingleSigned;
+pub use crate::decoded_dcc::DecodedDcc;
+pub use crate::error::{DccError, ErrorCode};
+pub use crate::hcert::{HealthCertificate, PersonName, RecoveryEntry, TestEntry, VaccinationEntry};
+pub use crate::issue::{write_dcc, Issuer};
+pub use crate::parse_options::ParseOptions;
+use crate::parsing_result::ParsingResult;
+pub use crate::read_dcc::read_dcc;
+pub use crate::revocation::{check_revocation, revocation_hashes, HashType, RevocationStore};
+pub use crate::rules::{
+    parse_rules, validate_rules, CertificateType, ExternalParameters, Rule, RuleDescription,
+    RuleOutcome, RuleResult, RuleStore,
+};
+pub use crate::schema::{validate_schema, SchemaViolation};
+pub use crate::schema_version::{check_schema_version, SchemaVersion};
+pub use crate::status::{overall_status, Status};
+#[cfg(any(test, feature = "test-data"))]
+pub use crate::test_data::{Fixture, FixtureKind, TestDataGenerator};
+pub use crate::trust_store::TrustStore;
+pub use crate::uvci::{luhn_mod_n, parse_uvci, Uvci};
+pub use crate::validity::Validity;
+pub use crate::value_sets::{ValueSet, ValueSetEntry, ValueSets};
+use crate::verifier::decode_and_verify;
+pub use crate::verifier::DccVerifier;
+use crate::verify::KeyCache;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
-use x509_parser::prelude::*;
 
 ///
 /// Parse a European Digital Covid Certificate (DCC)
@@ -24,106 +72,76 @@ use x509_parser::prelude::*;
 ///
 #[wasm_bindgen]
 pub fn parse(dcc_certificate: &str) -> ParsingResult {
-    let result_builder = ParsingResultBuilder::new();
-
-    // base45 decode, zlib inflate, into cose
-    let cose = match read_dcc(dcc_certificate) {
-        Ok(cose) => cose,
-        Err(e) => {
-            return result_builder
-                .fail_with_error(&format!("Couldn't parse COSE message: {}", e))
-                .build()
-        }
-    };
-
-    // Parse cbor payload into json
-    let json = match JsValue::from_serde(cose.payload()) {
-        Ok(json) => json,
-        Err(_e) => {
-            return result_builder
-                .fail_with_error("Error on converting the payload to JSON")
-                .build()
-        }
-    };
-
-    let kid = cose.kid().unwrap_or_else(String::new);
-    let alg = cose.alg().unwrap_or(0);
-
-    // Parsed successfully
-    let parsed_successful = result_builder.success().kid(&kid).alg(alg).data(json);
-
-    let issuer_cert = match find_issuer_cert(&kid) {
-        Some(c) => c,
-        None => {
-            return parsed_successful
-                .signature_error(&format!(
-                    "No public certificate known for issuer with kid {}",
-                    kid
-                ))
-                .build()
-        }
-    };
-
-    // Base64 decode issuer certificate
-    let issuer_cert = match base64::decode(issuer_cert) {
-        Ok(c) => c,
-        Err(e) => {
-            return parsed_successful
-                .signature_error(&format!("Error on base64 decoding issuer cert: {}", e))
-                .build()
-        }
-    };
+    parse_with_options(dcc_certificate, &ParseOptions::new())
+}
 
-    // Parse issuer certificate
-    let x509cert = match X509Certificate::from_der(&issuer_cert) {
-        Ok(c) => c.1,
-        Err(e) => {
-            return parsed_successful
-                .signature_error(&format!("Couldn't load issuer cert: {}", e))
-                .build()
-        }
-    };
+///
+/// Parse a European Digital Covid Certificate (DCC) with options
+///
+/// Same as `parse`, but the options determine which certificates
+/// are trusted to sign the dcc, which external additional
+/// authenticated data is used to verify the signature, the
+/// content of a detached payload, the moment in time to check
+/// the expiration and whether to validate against the schema.
+///
+#[wasm_bindgen]
+pub fn parse_with_options(dcc_certificate: &str, options: &ParseOptions) -> ParsingResult {
+    ParsingResult::from(decode_and_verify(
+        dcc_certificate,
+        options,
+        &mut KeyCache::new(),
+    ))
+}
 
-    // Get public key from issuer certificate
-    let verify_key =
-        match p256::PublicKey::from_sec1_bytes(x509cert.public_key().subject_public_key.data) {
-            Ok(public_key) => VerifyingKey::from(&public_key),
-            Err(e) => {
-                return parsed_successful
-                    .signature_error(&format!("Couldn't load public key: {}", e))
-                    .build()
-            }
-        };
+///
+/// Validate a health certificate with business rules
+///
+/// The rules are a JSON list in the format of the EU business rules, with
+/// the logic in CertLogic. The health certificate is the `health_certificate`
+/// of a ParsingResult. The external parameters contain the `validationClock`,
+/// `countryCode` and optionally `valueSets`, `exp`, `iat` and `issuerCountryCode`.
+/// When no value sets are given, the builtin value sets are used.
+///
+/// Returns the result of each rule: Passed, Failed or Open when the rule
+/// couldn't be evaluated.
+///
+#[wasm_bindgen]
+pub fn validate_business_rules(
+    rules: &str,
+    health_certificate: JsValue,
+    external: JsValue,
+) -> Result<JsValue, String> {
+    let rules = parse_rules(rules)?;
+    let health_certificate: serde_json::Value = from_js_value(&health_certificate)?;
+    let mut external: ExternalParameters = from_js_value(&external)?;
 
-    // The data to sign
-    let to_sign: [Value; 4] = cose.to_be_signed();
+    if external.value_sets.is_empty() {
+        external.value_sets = ValueSets::builtin().codes();
+    }
 
-    // Cbor encode to_sign
-    let mut cbor_encoded = Vec::new();
-    match ciborium::ser::into_writer(&to_sign, &mut cbor_encoded) {
-        Ok(..) => (),
-        Err(e) => {
-            return parsed_successful
-                .signature_error(&format!("Error on cbor encoding to sign object: {}", e))
-                .build()
-        }
-    };
+    to_js_value(&validate_rules(&rules, &health_certificate, &external))
+}
 
-    // Convert cose signature to ecdsa::Signature
-    let signature = match ecdsa::Signature::from_bytes(cose.signature()) {
-        Ok(s) => s,
-        Err(e) => {
-            return parsed_successful
-                .signature_error(&format!("Error on parsing signature bytes: {}", e))
-                .build()
-        }
-    };
+///
+/// Convert a serializable value to a javascript value, in the same way
+/// as JSON.parse would do
+///
+#[allow(deprecated)]
+pub(crate) fn to_js_value<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, String> {
+    match JsValue::from_serde(value) {
+        Ok(value) => Ok(value),
+        Err(e) => Err(format!("Error on converting to JSON: {}", e)),
+    }
+}
 
-    // Verify with public key if the given signature is valid
-    return match verify_key.verify(&cbor_encoded, &signature) {
-        Ok(..) => parsed_successful.signature_valid(true).build(),
-        Err(e) => parsed_successful
-            .signature_error(&format!("Error verifying signature: {}", e))
-            .build(),
-    };
+///
+/// Convert a javascript value to a deserializable value, in the same
+/// way as JSON.stringify would do
+///
+#[allow(deprecated)]
+pub(crate) fn from_js_value<T: DeserializeOwned>(value: &JsValue) -> Result<T, String> {
+    match value.into_serde() {
+        Ok(value) => Ok(value),
+        Err(e) => Err(format!("Error on parsing JSON: {}", e)),
+    }
 }
@@ -0,0 +1,175 @@
+use crate::revocation::RevocationStore;
+use crate::rules::RuleStore;
+use crate::trust_store::TrustStore;
+use crate::validity;
+use crate::value_sets::ValueSets;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// ParseOptions
+///
+/// Options to change how a Digital Covid Certificate is parsed
+/// and verified. By default the builtin certificates are trusted
+/// and no external additional authenticated data is used. Parsing
+/// is strict unless lenient mode is enabled. The validity in time
+/// is checked with the current time, without clock skew. The
+/// health certificate is not validated against the schema. Codes
+/// are resolved with the builtin value sets. No certificates are
+/// revoked. No business rules are validated.
+///
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct ParseOptions {
+    trust_store: TrustStore,
+    external_aad: Vec<u8>,
+    detached_payload: Option<Vec<u8>>,
+    lenient: bool,
+    now: Option<i64>,
+    clock_skew: i64,
+    validate_schema: bool,
+    value_sets: ValueSets,
+    revocation_store: RevocationStore,
+    rule_store: Option<RuleStore>,
+    country_code: String,
+}
+
+#[wasm_bindgen]
+impl ParseOptions {
+    /// The default options
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            trust_store: TrustStore::builtin(),
+            external_aad: Vec::new(),
+            detached_payload: None,
+            lenient: false,
+            now: None,
+            clock_skew: 0,
+            validate_schema: false,
+            value_sets: ValueSets::builtin(),
+            revocation_store: RevocationStore::new(),
+            rule_store: None,
+            country_code: String::new(),
+        }
+    }
+
+    /// Set the certificates used to verify the signature
+    pub fn set_trust_store(&mut self, trust_store: &TrustStore) {
+        self.trust_store = trust_store.clone();
+    }
+
+    /// Set the external additional authenticated data, which is
+    /// part of the signed data. A DCC has no external data.
+    pub fn set_external_aad(&mut self, external_aad: &[u8]) {
+        self.external_aad = external_aad.to_vec();
+    }
+
+    /// Set the content of a detached payload, for a COSE message
+    /// which doesn't contain the payload itself
+    pub fn set_detached_payload(&mut self, payload: &[u8]) {
+        self.detached_payload = Some(payload.to_vec());
+    }
+
+    /// In lenient mode, deviations from the specifications are tolerated
+    /// and reported as warnings, e.g. a DER encoded signature
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Set the moment of verification in seconds since epoch,
+    /// by default the current time of the clock is used
+    pub fn set_now(&mut self, now: f64) {
+        self.now = Some(now as i64);
+    }
+
+    /// Set the tolerated difference in seconds between the
+    /// clock of the issuer and the verifier
+    pub fn set_clock_skew(&mut self, clock_skew: u32) {
+        self.clock_skew = clock_skew as i64;
+    }
+
+    /// Validate the health certificate against the eu-dcc-schema,
+    /// violations are reported without failing the parsing
+    pub fn set_validate_schema(&mut self, validate_schema: bool) {
+        self.validate_schema = validate_schema;
+    }
+
+    /// Set the value sets used to resolve the codes into a display
+    pub fn set_value_sets(&mut self, value_sets: &ValueSets) {
+        self.value_sets = value_sets.clone();
+    }
+
+    /// Set the hashes of revoked certificates
+    pub fn set_revocation_store(&mut self, revocation_store: &RevocationStore) {
+        self.revocation_store = revocation_store.clone();
+    }
+
+    /// Validate the health certificate with the business rules
+    /// of the country of arrival
+    pub fn set_rules(&mut self, rule_store: &RuleStore, country_code: &str) {
+        self.rule_store = Some(rule_store.clone());
+        self.country_code = country_code.to_string();
+    }
+}
+
+impl ParseOptions {
+    /// The certificates used to verify the signature
+    pub fn trust_store(&self) -> &TrustStore {
+        &self.trust_store
+    }
+
+    /// The external additional authenticated data
+    pub fn external_aad(&self) -> &[u8] {
+        &self.external_aad
+    }
+
+    /// The content of a detached payload
+    pub fn detached_payload(&self) -> Option<&[u8]> {
+        self.detached_payload.as_deref()
+    }
+
+    /// Deviations from the specifications are tolerated
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// The moment of verification in seconds since epoch
+    pub fn now(&self) -> i64 {
+        self.now.unwrap_or_else(validity::now)
+    }
+
+    /// The tolerated clock skew in seconds
+    pub fn clock_skew(&self) -> i64 {
+        self.clock_skew
+    }
+
+    /// Validate the health certificate against the eu-dcc-schema
+    pub fn validate_schema(&self) -> bool {
+        self.validate_schema
+    }
+
+    /// The value sets used to resolve the codes into a display
+    pub fn value_sets(&self) -> &ValueSets {
+        &self.value_sets
+    }
+
+    /// The hashes of revoked certificates
+    pub fn revocation_store(&self) -> &RevocationStore {
+        &self.revocation_store
+    }
+
+    /// The business rules to validate, if any
+    pub fn rule_store(&self) -> Option<&RuleStore> {
+        self.rule_store.as_ref()
+    }
+
+    /// Country of arrival to select the business rules
+    pub fn country_code(&self) -> &str {
+        &self.country_code
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::new()
+    }
+}
@@ -1,5 +1,13 @@
+use crate::claims::CwtClaims;
+use crate::decoded_dcc::DecodedDcc;
+use crate::error::{DccError, ErrorCode};
+use crate::hcert::JsHealthCertificate;
+use crate::status::Status;
+use crate::to_js_value;
+use crate::uvci::Uvci;
+use crate::validity::Validity;
 use wasm_bindgen::prelude::wasm_bindgen;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 
 /// ParsingResult
 ///
@@ -16,10 +24,16 @@ use wasm_bindgen::JsValue;
 /// `signature_valid` should be true
 ///
 /// If one of these steps fail, the error property will be filled
-/// with an error message.
+/// with an error message. The `decode_error` and `verification_error`
+/// tell in which step it failed, so the data of a certificate which
+/// can't be verified can still be shown. The `status` combines all
+/// steps into one outcome.
 ///
 #[wasm_bindgen(getter_with_clone)]
 pub struct ParsingResult {
+    /// Overall status computed from all steps
+    pub status: Status,
+
     /// The algorithm used to sign the certificate
     pub algorithm: i32,
 
@@ -32,91 +46,108 @@ pub struct ParsingResult {
     /// Error message when parsing or verifying the signature fails
     pub error: String,
 
+    /// Stable code of the error, None when there is no error
+    pub error_code: ErrorCode,
+
+    /// Error while decoding the data, undefined when decoded successfully
+    pub decode_error: Option<DccError>,
+
+    /// Error while verifying the signature, validity or revocation,
+    /// undefined when not verified or verified successfully
+    pub verification_error: Option<DccError>,
+
     /// Data in the DCC
     pub data: JsValue,
 
-    /// signature valid
-    pub signature_valid: bool,
-}
+    /// Typed CWT claims in the DCC
+    pub claims: CwtClaims,
 
-/// A builder to construct a ParsingResult
-pub struct ParsingResultBuilder {
-    pub successful: bool,
-    pub error: String,
-    pub data: JsValue,
-    pub signature_valid: bool,
-    pub kid: String,
-    pub algorithm: i128,
-}
+    /// The health certificate according to the eu-dcc-schema,
+    /// undefined if it doesn't match the schema
+    pub health_certificate: JsHealthCertificate,
 
-impl ParsingResultBuilder {
-    /// ParsingResultBuilder is used to build
-    /// a ParsingResult. The ParsingResult is by default
-    /// unsuccessful.
-    pub fn new() -> ParsingResultBuilder {
-        ParsingResultBuilder {
-            successful: false,
-            error: String::from(""),
-            data: JsValue::null(),
-            signature_valid: false,
-            kid: String::from(""),
-            algorithm: 0,
-        }
-    }
+    /// The health certificate with codes replaced by their
+    /// display from the value sets, undefined if it doesn't match the schema
+    pub display: JsValue,
 
-    /// Set the successful on the ParsingResult to true
-    pub fn success(mut self) -> ParsingResultBuilder {
-        self.successful = true;
-        self
-    }
+    /// The parsed certificate identifier, undefined if it can't be parsed
+    pub uvci: Option<Uvci>,
 
-    /// Parsing failure with an error message
-    pub fn fail_with_error(mut self, message: &str) -> ParsingResultBuilder {
-        self.successful = false;
-        self.error = message.to_string();
-        self
-    }
+    /// Schema version of the health certificate, e.g. 1.3.0
+    pub schema_version: Option<String>,
 
-    /// Failure on verifying the signature
-    pub fn signature_error(mut self, message: &str) -> ParsingResultBuilder {
-        self.signature_valid = false;
-        self.error = message.to_string();
-        self
-    }
+    /// Deviations from the rules of the schema version
+    pub schema_deviations: Vec<String>,
 
-    /// Set data
-    pub fn data(mut self, data: JsValue) -> ParsingResultBuilder {
-        self.data = data;
-        self
-    }
+    /// Violations of the eu-dcc-schema with the path of the field and
+    /// a message, only when validation is enabled
+    pub schema_violations: JsValue,
 
-    /// Set when the signature is verified
-    pub fn signature_valid(mut self, valid: bool) -> ParsingResultBuilder {
-        self.signature_valid = valid;
-        self
-    }
+    /// signature valid
+    pub signature_valid: bool,
 
-    /// Set the kid
-    pub fn kid(mut self, kid: &str) -> ParsingResultBuilder {
-        self.kid = kid.to_string();
-        self
+    /// The certificate is found in a revocation list
+    pub revoked: bool,
+
+    /// Validity in time, expired or not yet valid
+    pub validity: Validity,
+
+    /// Results of the business rules, only when rules are set in the options
+    pub rule_results: JsValue,
+
+    /// Non fatal deviations from the specifications
+    pub warnings: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl ParsingResult {
+    /// Name of the error code, e.g. unknown-kid
+    #[wasm_bindgen(getter)]
+    pub fn error_name(&self) -> String {
+        self.error_code.name().to_string()
     }
 
-    /// Set the algorithm
-    pub fn alg(mut self, algorithm: i128) -> ParsingResultBuilder {
-        self.algorithm = algorithm;
-        self
+    /// Name of the status, e.g. SIGNATURE_INVALID
+    #[wasm_bindgen(getter)]
+    pub fn status_name(&self) -> String {
+        self.status.name().to_string()
     }
+}
+
+impl From<DecodedDcc> for ParsingResult {
+    fn from(decoded: DecodedDcc) -> Self {
+        let health_certificate = match &decoded.health_certificate {
+            Some(hc) => to_js_value(hc).unwrap_or(JsValue::UNDEFINED),
+            None => JsValue::UNDEFINED,
+        };
+        let display = match &decoded.display {
+            Some(display) => to_js_value(display).unwrap_or(JsValue::UNDEFINED),
+            None => JsValue::UNDEFINED,
+        };
 
-    /// Build ParsingResult
-    pub fn build(self) -> ParsingResult {
         ParsingResult {
-            successful: self.successful,
-            error: self.error,
-            data: self.data,
-            signature_valid: self.signature_valid,
-            kid: self.kid,
-            algorithm: self.algorithm as i32,
+            status: decoded.status,
+            successful: decoded.successful,
+            error: decoded.error,
+            error_code: decoded.error_code,
+            decode_error: decoded.decode_error,
+            verification_error: decoded.verification_error,
+            data: to_js_value(&decoded.data).unwrap_or(JsValue::NULL),
+            claims: decoded.claims,
+            health_certificate: health_certificate.unchecked_into(),
+            display,
+            uvci: decoded.uvci,
+            schema_version: decoded.schema_version,
+            schema_deviations: decoded.schema_deviations,
+            schema_violations: to_js_value(&decoded.schema_violations)
+                .unwrap_or(JsValue::UNDEFINED),
+            signature_valid: decoded.signature_valid,
+            revoked: decoded.revoked,
+            validity: decoded.validity,
+            kid: decoded.kid,
+            algorithm: decoded.algorithm,
+            rule_results: to_js_value(&decoded.rule_results).unwrap_or(JsValue::UNDEFINED),
+            warnings: decoded.warnings,
         }
     }
 }
@@ -1,43 +1,66 @@
+use crate::error::{DccError, ErrorCode, WithCode};
 use crate::CoseSingleSigned;
 use ciborium::value::Value;
 use flate2::read::ZlibDecoder;
 use std::convert::TryFrom;
 use std::io::Read;
 
-pub fn read_dcc(dcc_certificate: &str) -> Result<CoseSingleSigned, String> {
+pub fn read_dcc(dcc_certificate: &str) -> Result<CoseSingleSigned, DccError> {
     // Strip HC1: prefix
     let mut dcc_certificate: &str = dcc_certificate;
     if let Some(stripped) = dcc_certificate.strip_prefix("HC1:") {
         dcc_certificate = stripped
     }
 
-    // Base45 decode
-    let decoded = match base45::decode(dcc_certificate) {
-        Ok(d) => d,
-        Err(e) => return Err(format!("Error on base45 decode: {}", e)),
-    };
+    let decoded = base45_decode(dcc_certificate)?;
+    let decompressed = decompress(&decoded)?;
+    decode_cose(&decompressed)
+}
+
+///
+/// Base45 decode
+///
+pub(crate) fn base45_decode(data: &str) -> Result<Vec<u8>, DccError> {
+    match base45::decode(data) {
+        Ok(d) => Ok(d),
+        Err(e) => Err(format!("Error on base45 decode: {}", e)).code(ErrorCode::Base45),
+    }
+}
 
-    // Zlib deflate
+///
+/// Zlib deflate
+///
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, DccError> {
     let mut decompressed: Vec<u8> = Vec::new();
-    let mut decompressor = ZlibDecoder::new(&decoded[..]);
+    let mut decompressor = ZlibDecoder::new(data);
     match decompressor.read_to_end(&mut decompressed) {
-        Ok(_result) => (),
-        Err(e) => return Err(format!("Error on zlib decompressing: {}", e)),
+        Ok(_result) => Ok(decompressed),
+        Err(e) => Err(format!("Error on zlib decompressing: {}", e)).code(ErrorCode::Zlib),
     }
+}
 
-    // Decode cbor in a cose message
-    let cose: [Value; 4] = match ciborium::de::from_reader(&decompressed[..]) {
+///
+/// Decode cbor in a cose message
+///
+pub(crate) fn decode_cose(data: &[u8]) -> Result<CoseSingleSigned, DccError> {
+    let cose: [Value; 4] = match ciborium::de::from_reader(data) {
         Ok(cose_message) => cose_message,
-        Err(_e) => return Err(String::from("The cbor data is not an array")),
+        Err(_e) => {
+            return Err(DccError::new(
+                ErrorCode::Cbor,
+                "The cbor data is not an array",
+            ))
+        }
     };
 
     match CoseSingleSigned::try_from(cose) {
         Ok(cose) => Ok(cose),
-        Err(e) => Err(format!("Couldn't parse COSE message: {}", e)),
+        Err(e) => Err(format!("Couldn't parse COSE message: {}", e)).code(ErrorCode::CoseStructure),
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -56,6 +79,12 @@ mod tests {
         )
     }
 
+    #[test]
+    fn errors_have_a_code() {
+        assert_eq!(read_dcc("a").err().map(|e| e.code), Some(ErrorCode::Base45));
+        assert_eq!(read_dcc("00").err().map(|e| e.code), Some(ErrorCode::Zlib));
+    }
+
     #[test]
     fn it_reads_alg() {
         assert_eq!(read_dcc(TEST_DCC).unwrap().alg(), Some(-7))
@@ -0,0 +1,568 @@
+use crate::bloom_filter::BloomFilter;
+use crate::claims::CwtClaims;
+use crate::cose::CoseSingleSigned;
+use crate::verify::certificate_kid;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Revocation hashes are truncated to the first 16 bytes
+pub const HASH_LENGTH: usize = 16;
+
+///
+/// Type of hash in a revocation list
+///
+/// https://github.com/eu-digital-green-certificates/dgc-overview/blob/main/guides/revocation.md
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashType {
+    /// SHA-256 of the UVCI
+    #[serde(rename = "UCI")]
+    Uci,
+    /// SHA-256 of the issuing country code followed by the UVCI
+    #[serde(rename = "COUNTRYCODEUCI")]
+    CountryCodeUci,
+    /// SHA-256 of the signature, the r part for ECDSA
+    #[serde(rename = "SIGNATURE")]
+    Signature,
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HashType::Uci => "UCI",
+            HashType::CountryCodeUci => "COUNTRYCODEUCI",
+            HashType::Signature => "SIGNATURE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///
+/// A batch of revoked hashes in the format of the EU gateway
+///
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RevocationBatch {
+    kid: String,
+    hash_type: HashType,
+    entries: Vec<RevocationEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevocationEntry {
+    hash: String,
+}
+
+/// RevocationStore
+///
+/// The hashes of revoked certificates, by kid of the document signer
+/// certificate. The hashes of a kid are stored in partitions, which are
+/// updated independently. A partition contains hashes of one type, which
+/// start with the prefix of the partition, either as a list of hashes or as a
+/// Bloom filter. Hashes are truncated to 16 bytes.
+///
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RevocationStore {
+    partitions: HashMap<String, Vec<Partition>>,
+}
+
+///
+/// A partition of the revocation hashes of a kid
+///
+#[derive(Clone, Debug)]
+struct Partition {
+    id: String,
+    hash_type: HashType,
+    prefix: Vec<u8>,
+    content: PartitionContent,
+}
+
+#[derive(Clone, Debug)]
+enum PartitionContent {
+    /// Sorted list of hashes
+    Hashes(Vec<[u8; HASH_LENGTH]>),
+    BloomFilter(BloomFilter),
+}
+
+impl Partition {
+    fn contains(&self, hash_type: HashType, hash: &[u8; HASH_LENGTH]) -> bool {
+        if self.hash_type != hash_type || !hash.starts_with(&self.prefix) {
+            return false;
+        }
+
+        match &self.content {
+            PartitionContent::Hashes(hashes) => hashes.binary_search(hash).is_ok(),
+            PartitionContent::BloomFilter(filter) => filter.contains(hash),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RevocationStore {
+    /// An empty RevocationStore
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RevocationStore {
+        RevocationStore {
+            partitions: HashMap::new(),
+        }
+    }
+
+    /// Add a revoked hash for the kid of a document signer certificate,
+    /// to the partition without prefix of the hash type
+    pub fn add_hash(&mut self, kid: &str, hash_type: HashType, hash: &str) -> Result<(), String> {
+        let hash = match base64::decode(hash) {
+            Ok(hash) => truncate(&hash)?,
+            Err(e) => return Err(format!("Invalid revocation hash {}: {}", hash, e)),
+        };
+
+        let id = hash_type.to_string();
+        let partitions = self.partitions.entry(kid.to_string()).or_default();
+        let partition = match partitions.iter_mut().find(|p| p.id == id) {
+            Some(partition) => partition,
+            None => {
+                partitions.push(Partition {
+                    id,
+                    hash_type,
+                    prefix: Vec::new(),
+                    content: PartitionContent::Hashes(Vec::new()),
+                });
+                partitions.last_mut().expect("partition is added")
+            }
+        };
+
+        match &mut partition.content {
+            PartitionContent::Hashes(hashes) => {
+                if let Err(index) = hashes.binary_search(&hash) {
+                    hashes.insert(index, hash);
+                }
+            }
+            PartitionContent::BloomFilter(filter) => filter.insert(&hash),
+        }
+        Ok(())
+    }
+
+    /// Add a batch of revoked hashes, in the JSON format of the EU gateway
+    pub fn add_batch(&mut self, json: &str) -> Result<(), String> {
+        let batch: RevocationBatch = match serde_json::from_str(json) {
+            Ok(batch) => batch,
+            Err(e) => return Err(format!("Invalid revocation batch: {}", e)),
+        };
+
+        for entry in batch.entries {
+            self.add_hash(&batch.kid, batch.hash_type, &entry.hash)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Set a partition with a list of hashes, the concatenated 16 byte
+    /// hashes starting with the prefix. Replaces the partition with the same
+    /// id of the kid.
+    ///
+    pub fn set_hash_partition(
+        &mut self,
+        kid: &str,
+        id: &str,
+        hash_type: HashType,
+        prefix: &[u8],
+        hashes: &[u8],
+    ) -> Result<(), String> {
+        if hashes.len() % HASH_LENGTH != 0 {
+            return Err(format!(
+                "Hashes of partition {} are not a multiple of {} bytes",
+                id, HASH_LENGTH
+            ));
+        }
+
+        let mut hashes = hashes
+            .chunks(HASH_LENGTH)
+            .map(truncate)
+            .collect::<Result<Vec<[u8; HASH_LENGTH]>, String>>()?;
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        self.set_partition(kid, id, hash_type, prefix, PartitionContent::Hashes(hashes));
+        Ok(())
+    }
+
+    ///
+    /// Set a partition with a Bloom filter of the hashes starting with the
+    /// prefix, in the format of the EU gateway: the data of the filter as
+    /// big endian bytes, with its number of bits and hash functions.
+    /// Replaces the partition with the same id of the kid.
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_bloom_filter_partition(
+        &mut self,
+        kid: &str,
+        id: &str,
+        hash_type: HashType,
+        prefix: &[u8],
+        bits: &[u8],
+        bit_count: u32,
+        hash_count: u32,
+    ) -> Result<(), String> {
+        let filter = BloomFilter::from_bits(bits, bit_count, hash_count)?;
+        self.set_partition(
+            kid,
+            id,
+            hash_type,
+            prefix,
+            PartitionContent::BloomFilter(filter),
+        );
+        Ok(())
+    }
+
+    /// Remove a partition of a kid, returns if the partition existed
+    pub fn remove_partition(&mut self, kid: &str, id: &str) -> bool {
+        let partitions = match self.partitions.get_mut(kid) {
+            Some(partitions) => partitions,
+            None => return false,
+        };
+
+        let count = partitions.len();
+        partitions.retain(|partition| partition.id != id);
+        let removed = partitions.len() != count;
+
+        if partitions.is_empty() {
+            self.partitions.remove(kid);
+        }
+        removed
+    }
+
+    /// Remove all partitions of a kid
+    pub fn remove_kid(&mut self, kid: &str) {
+        self.partitions.remove(kid);
+    }
+
+    /// Whether the store has no revoked hashes
+    pub fn is_empty(&self) -> bool {
+        self.partitions.is_empty()
+    }
+}
+
+impl RevocationStore {
+    /// The type of the first revoked hash, None if none of
+    /// the hashes is revoked for the kid
+    pub fn find_revoked(&self, kid: &str, hashes: &[(HashType, Vec<u8>)]) -> Option<HashType> {
+        let partitions = self.partitions.get(kid)?;
+
+        hashes
+            .iter()
+            .filter_map(|(hash_type, hash)| Some((*hash_type, truncate(hash).ok()?)))
+            .find(|(hash_type, hash)| {
+                partitions
+                    .iter()
+                    .any(|partition| partition.contains(*hash_type, hash))
+            })
+            .map(|(hash_type, _hash)| hash_type)
+    }
+
+    fn set_partition(
+        &mut self,
+        kid: &str,
+        id: &str,
+        hash_type: HashType,
+        prefix: &[u8],
+        content: PartitionContent,
+    ) {
+        let partition = Partition {
+            id: id.to_string(),
+            hash_type,
+            prefix: prefix.to_vec(),
+            content,
+        };
+
+        let partitions = self.partitions.entry(kid.to_string()).or_default();
+        match partitions.iter_mut().find(|p| p.id == id) {
+            Some(existing) => *existing = partition,
+            None => partitions.push(partition),
+        }
+    }
+}
+
+///
+/// The revocation hashes of a DCC, truncated to 16 bytes
+///
+/// The UVCI is the certificate identifier of the health certificate and
+/// the country code is the issuer claim. Both hashes are absent when the
+/// claims don't contain them.
+///
+pub fn revocation_hashes(cose: &CoseSingleSigned, claims: &CwtClaims) -> Vec<(HashType, Vec<u8>)> {
+    let mut hashes = Vec::new();
+
+    if let Some(uvci) = claims.certificate_identifier() {
+        hashes.push((HashType::Uci, Sha256::digest(uvci)[..HASH_LENGTH].to_vec()));
+
+        if let Some(country) = &claims.issuer {
+            let country_code_uci = format!("{}{}", country, uvci);
+            hashes.push((
+                HashType::CountryCodeUci,
+                Sha256::digest(country_code_uci)[..HASH_LENGTH].to_vec(),
+            ));
+        }
+    }
+
+    // ECDSA signatures are r||s, only r is hashed
+    let signature = cose.signature();
+    let signature = match signature.len() {
+        64 | 96 | 132 => &signature[..signature.len() / 2],
+        _ => signature,
+    };
+    hashes.push((
+        HashType::Signature,
+        Sha256::digest(signature)[..HASH_LENGTH].to_vec(),
+    ));
+
+    hashes
+}
+
+///
+/// Check if a DCC is revoked, returns the type of the revoked hash
+///
+/// The hashes are looked up by the kid of the document signer certificate,
+/// which is calculated from an embedded certificate when the kid is absent.
+///
+pub fn check_revocation(
+    cose: &CoseSingleSigned,
+    claims: &CwtClaims,
+    store: &RevocationStore,
+) -> Option<HashType> {
+    if store.is_empty() {
+        return None;
+    }
+
+    let kid = cose
+        .kid()
+        .or_else(|| cose.x5chain().first().map(|cert| certificate_kid(cert)))?;
+
+    store.find_revoked(&kid, &revocation_hashes(cose, claims))
+}
+
+fn truncate(hash: &[u8]) -> Result<[u8; HASH_LENGTH], String> {
+    match hash.get(..HASH_LENGTH) {
+        Some(hash) => Ok(hash.try_into().expect("hash has 16 bytes")),
+        None => Err(format!(
+            "Revocation hash of {} bytes is shorter than {} bytes",
+            hash.len(),
+            HASH_LENGTH
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_dcc::read_dcc;
+    use ciborium::value::Value;
+    use std::convert::TryFrom;
+
+    const TEST_DCC: &str = "6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1";
+
+    const KID: &str = "DEsVUSvpFAE=";
+
+    fn test_dcc() -> (CoseSingleSigned, CwtClaims) {
+        let cose = read_dcc(TEST_DCC).unwrap();
+        let claims = CwtClaims::try_from(cose.payload().unwrap()).unwrap();
+        (cose, claims)
+    }
+
+    fn hash(hash_type: HashType) -> String {
+        let (cose, claims) = test_dcc();
+        let hashes = revocation_hashes(&cose, &claims);
+        let (_, hash) = hashes.iter().find(|(t, _)| *t == hash_type).unwrap();
+        base64::encode(hash)
+    }
+
+    #[test]
+    fn it_calculates_the_hashes() {
+        let (cose, claims) = test_dcc();
+        let hashes = revocation_hashes(&cose, &claims);
+
+        let uci = Sha256::digest("URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W");
+        let country_code_uci = Sha256::digest("DEURN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W");
+        let signature = Sha256::digest(&cose.signature()[..32]);
+
+        assert_eq!(
+            hashes,
+            vec![
+                (HashType::Uci, uci[..16].to_vec()),
+                (HashType::CountryCodeUci, country_code_uci[..16].to_vec()),
+                (HashType::Signature, signature[..16].to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_is_revoked_by_each_hash_type() {
+        let (cose, claims) = test_dcc();
+
+        for hash_type in [HashType::Uci, HashType::CountryCodeUci, HashType::Signature] {
+            let mut store = RevocationStore::new();
+            store.add_hash(KID, hash_type, &hash(hash_type)).unwrap();
+            assert_eq!(check_revocation(&cose, &claims, &store), Some(hash_type));
+        }
+    }
+
+    #[test]
+    fn it_is_revoked_when_the_hcert_deviates_from_the_schema() {
+        let (cose, mut claims) = test_dcc();
+        if let Value::Map(hcert) = &mut claims.hcert {
+            hcert.retain(|entry| entry.0 != Value::from("nam"));
+            hcert.push((Value::from("extra"), Value::from(true)));
+        }
+        assert!(claims.health_certificate().is_err());
+
+        let mut store = RevocationStore::new();
+        store
+            .add_hash(KID, HashType::Uci, &hash(HashType::Uci))
+            .unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::Uci)
+        );
+    }
+
+    #[test]
+    fn full_hashes_are_truncated() {
+        let (cose, claims) = test_dcc();
+        let uci = Sha256::digest("URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W");
+
+        let mut store = RevocationStore::new();
+        store
+            .add_hash(KID, HashType::Uci, &base64::encode(uci))
+            .unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::Uci)
+        );
+    }
+
+    #[test]
+    fn it_is_not_revoked_for_another_kid_or_hash_type() {
+        let (cose, claims) = test_dcc();
+
+        let mut store = RevocationStore::new();
+        store
+            .add_hash("AAAAAAAAAAA=", HashType::Uci, &hash(HashType::Uci))
+            .unwrap();
+        store
+            .add_hash(KID, HashType::Signature, &hash(HashType::Uci))
+            .unwrap();
+        assert_eq!(check_revocation(&cose, &claims, &store), None);
+    }
+
+    fn prefixed(hash_type: HashType) -> (Vec<u8>, Vec<u8>) {
+        let hash = base64::decode(hash(hash_type)).unwrap();
+        (hash[..1].to_vec(), hash)
+    }
+
+    #[test]
+    fn it_is_revoked_by_a_hash_partition() {
+        let (cose, claims) = test_dcc();
+        let (prefix, hash) = prefixed(HashType::Signature);
+
+        let mut store = RevocationStore::new();
+        let mut hashes = vec![0; 16];
+        hashes.extend(&hash);
+        store
+            .set_hash_partition(KID, "a", HashType::Signature, &prefix, &hashes)
+            .unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::Signature)
+        );
+
+        // A hash only matches the partition of its prefix
+        let other_prefix = [prefix[0].wrapping_add(1)];
+        store
+            .set_hash_partition(KID, "a", HashType::Signature, &other_prefix, &hashes)
+            .unwrap();
+        assert_eq!(check_revocation(&cose, &claims, &store), None);
+    }
+
+    #[test]
+    fn it_is_revoked_by_a_bloom_filter_partition() {
+        let (cose, claims) = test_dcc();
+        let (prefix, hash) = prefixed(HashType::Uci);
+
+        let mut filter = BloomFilter::with_rate(100, 0.001);
+        filter.insert(&hash);
+
+        let mut store = RevocationStore::new();
+        store
+            .set_bloom_filter_partition(
+                KID,
+                "b",
+                HashType::Uci,
+                &prefix,
+                filter.bits(),
+                filter.bit_count(),
+                filter.hash_count(),
+            )
+            .unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::Uci)
+        );
+    }
+
+    #[test]
+    fn partitions_are_updated_independently() {
+        let (cose, claims) = test_dcc();
+        let (_, uci) = prefixed(HashType::Uci);
+        let (_, signature) = prefixed(HashType::Signature);
+
+        let mut store = RevocationStore::new();
+        store
+            .set_hash_partition(KID, "a", HashType::Uci, &[], &uci)
+            .unwrap();
+        store
+            .set_hash_partition(KID, "b", HashType::Signature, &[], &signature)
+            .unwrap();
+
+        // Replace partition a, partition b remains
+        store
+            .set_hash_partition(KID, "a", HashType::Uci, &[], &[0; 16])
+            .unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::Signature)
+        );
+
+        assert!(store.remove_partition(KID, "b"));
+        assert!(!store.remove_partition(KID, "b"));
+        assert_eq!(check_revocation(&cose, &claims, &store), None);
+
+        store.remove_kid(KID);
+        assert!(store.is_empty());
+        assert!(store
+            .set_hash_partition(KID, "c", HashType::Uci, &[], &[0; 15])
+            .is_err());
+    }
+
+    #[test]
+    fn it_reads_a_batch() {
+        let (cose, claims) = test_dcc();
+        let batch = format!(
+            r#"{{"country": "DE", "expires": "2022-06-01T00:00:00Z", "kid": "{}",
+            "hashType": "COUNTRYCODEUCI", "entries": [{{"hash": "{}"}}]}}"#,
+            KID,
+            hash(HashType::CountryCodeUci)
+        );
+
+        let mut store = RevocationStore::new();
+        store.add_batch(&batch).unwrap();
+        assert_eq!(
+            check_revocation(&cose, &claims, &store),
+            Some(HashType::CountryCodeUci)
+        );
+        assert!(RevocationStore::new().add_batch("{}").is_err());
+    }
+}
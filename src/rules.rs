@@ -0,0 +1,698 @@
+use crate::certlogic::{evaluate, is_truthy, parse_date_time};
+use crate::claims::CwtClaims;
+use crate::value_sets::ValueSets;
+use crate::{from_js_value, to_js_value};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+///
+/// A business rule in the format of the EU business rules
+///
+/// https://github.com/ehn-dcc-development/dgc-business-rules
+///
+/// The logic of the rule is a CertLogic expression, which
+/// is truthy when the DCC passes the rule.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Rule {
+    pub identifier: String,
+    #[serde(rename = "Type")]
+    pub rule_type: String,
+    pub country: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub version: String,
+    #[serde(default)]
+    pub schema_version: String,
+    #[serde(default)]
+    pub engine: String,
+    #[serde(default)]
+    pub engine_version: String,
+    pub certificate_type: String,
+    #[serde(default)]
+    pub description: Vec<RuleDescription>,
+    pub valid_from: String,
+    pub valid_to: String,
+    #[serde(default)]
+    pub affected_fields: Vec<String>,
+    pub logic: Value,
+}
+
+///
+/// Description of a rule in a language
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RuleDescription {
+    pub lang: String,
+    pub desc: String,
+}
+
+///
+/// Parameters of the validation which are not part of the DCC
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalParameters {
+    /// Moment of validation as date-time
+    pub validation_clock: String,
+    /// Codes of the value sets, by value set id
+    #[serde(default)]
+    pub value_sets: HashMap<String, Vec<String>>,
+    /// Country of arrival
+    pub country_code: String,
+    /// Expiration of the DCC as date-time
+    #[serde(default)]
+    pub exp: String,
+    /// Issued at of the DCC as date-time
+    #[serde(default)]
+    pub iat: String,
+    /// Country of the issuer of the DCC
+    #[serde(default)]
+    pub issuer_country_code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+impl ExternalParameters {
+    /// Parameters to validate at a moment for a country of arrival
+    pub fn new(
+        validation_clock: &str,
+        country_code: &str,
+        value_sets: &ValueSets,
+    ) -> ExternalParameters {
+        ExternalParameters {
+            validation_clock: String::from(validation_clock),
+            value_sets: value_sets.codes(),
+            country_code: String::from(country_code),
+            exp: String::new(),
+            iat: String::new(),
+            issuer_country_code: String::new(),
+            kid: None,
+            region: None,
+        }
+    }
+
+    /// Parameters to validate a DCC at a moment in seconds since epoch,
+    /// with the expiration, issued at and issuer of its claims
+    pub fn of_claims(
+        now: i64,
+        country_code: &str,
+        value_sets: &ValueSets,
+        claims: &CwtClaims,
+    ) -> ExternalParameters {
+        ExternalParameters {
+            exp: claims.expiration.map(date_time).unwrap_or_default(),
+            iat: claims.issued_at.map(date_time).unwrap_or_default(),
+            issuer_country_code: claims.issuer.clone().unwrap_or_default(),
+            ..ExternalParameters::new(&date_time(now), country_code, value_sets)
+        }
+    }
+}
+
+///
+/// Seconds since epoch as date-time in UTC
+///
+fn date_time(seconds: i64) -> String {
+    DateTime::<Utc>::from_timestamp(seconds, 0)
+        .map(|date_time| date_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+///
+/// Outcome of a rule
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleOutcome {
+    /// The DCC passes the rule
+    Passed,
+    /// The DCC fails the rule
+    Failed,
+    /// The rule couldn't be evaluated
+    Open,
+}
+
+///
+/// Result of a rule for a DCC
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RuleResult {
+    pub identifier: String,
+    pub version: String,
+    pub country: String,
+    pub description: String,
+    pub outcome: RuleOutcome,
+    /// Why the rule couldn't be evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+///
+/// Parse a list of rules in JSON
+///
+pub fn parse_rules(json: &str) -> Result<Vec<Rule>, String> {
+    match serde_json::from_str(json) {
+        Ok(rules) => Ok(rules),
+        Err(e) => Err(format!("Invalid rules: {}", e)),
+    }
+}
+
+///
+/// Evaluate the rules for a health certificate
+///
+/// The health certificate is available to the logic as `payload`,
+/// the external parameters as `external`.
+///
+pub fn validate_rules(
+    rules: &[Rule],
+    health_certificate: &Value,
+    external: &ExternalParameters,
+) -> Vec<RuleResult> {
+    let data = json!({
+        "payload": health_certificate,
+        "external": external,
+    });
+
+    rules
+        .iter()
+        .map(|rule| {
+            let (outcome, error) = match evaluate(&rule.logic, &data) {
+                Ok(result) if is_truthy(&result) => (RuleOutcome::Passed, None),
+                Ok(_result) => (RuleOutcome::Failed, None),
+                Err(e) => (RuleOutcome::Open, Some(e)),
+            };
+
+            RuleResult {
+                identifier: rule.identifier.clone(),
+                version: rule.version.clone(),
+                country: rule.country.clone(),
+                description: rule.description("en"),
+                outcome,
+                error,
+            }
+        })
+        .collect()
+}
+
+impl Rule {
+    /// Description in a language, the first description if not available
+    pub fn description(&self, lang: &str) -> String {
+        self.description
+            .iter()
+            .find(|description| description.lang == lang)
+            .or_else(|| self.description.first())
+            .map(|description| description.desc.clone())
+            .unwrap_or_default()
+    }
+}
+
+///
+/// Type of certificate a rule applies to
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CertificateType {
+    /// All types of certificates
+    General,
+    Vaccination,
+    Test,
+    Recovery,
+}
+
+impl CertificateType {
+    /// Type of a health certificate, by the group it contains
+    pub fn of(health_certificate: &Value) -> Option<CertificateType> {
+        if health_certificate.get("v").is_some() {
+            Some(CertificateType::Vaccination)
+        } else if health_certificate.get("t").is_some() {
+            Some(CertificateType::Test)
+        } else if health_certificate.get("r").is_some() {
+            Some(CertificateType::Recovery)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CertificateType::General => "General",
+            CertificateType::Vaccination => "Vaccination",
+            CertificateType::Test => "Test",
+            CertificateType::Recovery => "Recovery",
+        }
+    }
+}
+
+/// RuleStore
+///
+/// The business rules of one or more countries, possibly in several
+/// versions. For a validation, the applicable version of each rule is
+/// selected by the moment of validation, the country and region of
+/// arrival and the type of certificate.
+///
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RuleStore {
+    rules: Vec<Rule>,
+}
+
+#[wasm_bindgen]
+impl RuleStore {
+    /// An empty RuleStore
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RuleStore {
+        RuleStore { rules: Vec::new() }
+    }
+
+    /// Add a JSON list of rules in the format of the EU business rules
+    pub fn add_rules(&mut self, json: &str) -> Result<(), String> {
+        let rules = parse_rules(json)?;
+        self.rules.extend(rules);
+        Ok(())
+    }
+
+    /// Number of rules in the store, all versions included
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether the store has no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The applicable rules at a date-time for a country and region of
+    /// arrival, a type of certificate and optionally the issuer country
+    #[wasm_bindgen(js_name = select)]
+    pub fn select_js(
+        &self,
+        date_time: &str,
+        country: &str,
+        region: Option<String>,
+        certificate_type: CertificateType,
+        issuer_country: Option<String>,
+    ) -> Result<JsValue, String> {
+        let date_time = parse_date_time(date_time)?;
+        let rules = self.select(
+            &date_time,
+            country,
+            region.as_deref(),
+            certificate_type,
+            issuer_country.as_deref(),
+        );
+        to_js_value(&rules)
+    }
+
+    /// Validate a health certificate with the applicable rules, selected by the validation
+    /// clock, country code, region and issuer country code of the external parameters
+    #[wasm_bindgen(js_name = validate)]
+    pub fn validate_js(
+        &self,
+        health_certificate: JsValue,
+        external: JsValue,
+    ) -> Result<JsValue, String> {
+        let health_certificate: Value = from_js_value(&health_certificate)?;
+        let mut external: ExternalParameters = from_js_value(&external)?;
+
+        if external.value_sets.is_empty() {
+            external.value_sets = ValueSets::builtin().codes();
+        }
+
+        to_js_value(&self.validate(&health_certificate, &external)?)
+    }
+}
+
+impl RuleStore {
+    /// All rules in the store
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    ///
+    /// Select the applicable rules
+    ///
+    /// A rule applies when it's valid at the date-time and it's of the type
+    /// of certificate or General. Acceptance rules apply when they're of the
+    /// country of arrival and of the region when the rule has a region.
+    /// Invalidation rules apply when they're of the issuer country of the
+    /// DCC. Of each rule the highest applicable version is selected.
+    ///
+    pub fn select(
+        &self,
+        date_time: &DateTime<FixedOffset>,
+        country: &str,
+        region: Option<&str>,
+        certificate_type: CertificateType,
+        issuer_country: Option<&str>,
+    ) -> Vec<&Rule> {
+        let mut selected: Vec<&Rule> = Vec::new();
+
+        let applicable = self.rules.iter().filter(|rule| {
+            let of_country = match rule.rule_type.as_str() {
+                "Acceptance" => {
+                    rule.country == country
+                        && match rule.region.as_deref() {
+                            None | Some("") => true,
+                            Some(rule_region) => region == Some(rule_region),
+                        }
+                }
+                "Invalidation" => issuer_country == Some(rule.country.as_str()),
+                _ => false,
+            };
+
+            of_country
+                && rule.is_valid_at(date_time)
+                && (rule.certificate_type == CertificateType::General.name()
+                    || rule.certificate_type == certificate_type.name())
+        });
+
+        for rule in applicable {
+            match selected
+                .iter_mut()
+                .find(|other| other.identifier == rule.identifier)
+            {
+                Some(other) if version(&rule.version) > version(&other.version) => *other = rule,
+                Some(_) => {}
+                None => selected.push(rule),
+            }
+        }
+
+        selected
+    }
+
+    ///
+    /// Validate a health certificate with the applicable rules, selected by the
+    /// validation clock, country code, region and issuer country code of the
+    /// external parameters
+    ///
+    pub fn validate(
+        &self,
+        health_certificate: &Value,
+        external: &ExternalParameters,
+    ) -> Result<Vec<RuleResult>, String> {
+        let date_time = parse_date_time(&external.validation_clock)?;
+        let certificate_type = match CertificateType::of(health_certificate) {
+            Some(certificate_type) => certificate_type,
+            None => return Err(String::from("Health certificate has no v, t or r group")),
+        };
+
+        let rules: Vec<Rule> = self
+            .select(
+                &date_time,
+                &external.country_code,
+                external.region.as_deref(),
+                certificate_type,
+                Some(&external.issuer_country_code),
+            )
+            .into_iter()
+            .cloned()
+            .collect();
+
+        Ok(validate_rules(&rules, health_certificate, external))
+    }
+}
+
+impl Rule {
+    /// Whether the date-time is within the validity window of the rule
+    pub fn is_valid_at(&self, date_time: &DateTime<FixedOffset>) -> bool {
+        match (
+            parse_date_time(&self.valid_from),
+            parse_date_time(&self.valid_to),
+        ) {
+            (Ok(valid_from), Ok(valid_to)) => valid_from <= *date_time && *date_time <= valid_to,
+            _ => false,
+        }
+    }
+}
+
+///
+/// A version as a list of numbers, to compare 1.10.0 and 1.9.0
+///
+fn version(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"[{
+        "Identifier": "VR-NL-0001",
+        "Type": "Acceptance",
+        "Country": "NL",
+        "Version": "1.0.0",
+        "SchemaVersion": "1.0.0",
+        "Engine": "CERTLOGIC",
+        "EngineVersion": "0.7.5",
+        "CertificateType": "Vaccination",
+        "Description": [{"lang": "en", "desc": "The vaccination course must be completed."}],
+        "ValidFrom": "2021-07-01T00:00:00Z",
+        "ValidTo": "2030-06-01T00:00:00Z",
+        "AffectedFields": ["v.0.dn", "v.0.sd"],
+        "Logic": {"if": [
+            {"var": "payload.v.0"},
+            {">=": [{"var": "payload.v.0.dn"}, {"var": "payload.v.0.sd"}]},
+            true
+        ]}
+    }, {
+        "Identifier": "VR-NL-0002",
+        "Type": "Acceptance",
+        "Country": "NL",
+        "Version": "1.0.0",
+        "CertificateType": "Vaccination",
+        "ValidFrom": "2021-07-01T00:00:00Z",
+        "ValidTo": "2030-06-01T00:00:00Z",
+        "Logic": {"and": [
+            {"in": [{"var": "payload.v.0.mp"}, {"var": "external.valueSets.vaccines-covid-19-names"}]},
+            {"not-before": [
+                {"var": "external.validationClock"},
+                {"plusTime": [{"var": "payload.v.0.dt"}, 14, "day"]}
+            ]}
+        ]}
+    }]"#;
+
+    fn vaccination(dn: u32) -> Value {
+        json!({"ver": "1.3.0", "nam": {"fnt": "DOE"}, "dob": "1990",
+            "v": [{"tg": "840539006", "vp": "1119349007", "mp": "EU/1/20/1528",
+            "ma": "ORG-100030215", "dn": dn, "sd": 2, "dt": "2021-06-01",
+            "co": "NL", "is": "Ministry", "ci": "URN:UVCI:01:NL:123"}]})
+    }
+
+    fn outcomes(results: &[RuleResult]) -> Vec<RuleOutcome> {
+        results.iter().map(|result| result.outcome).collect()
+    }
+
+    #[test]
+    fn it_parses_the_rules() {
+        let rules = parse_rules(RULES).unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].identifier, "VR-NL-0001");
+        assert_eq!(rules[0].rule_type, "Acceptance");
+        assert_eq!(
+            rules[0].description("nl"),
+            "The vaccination course must be completed."
+        );
+    }
+
+    #[test]
+    fn it_validates_a_vaccination() {
+        let rules = parse_rules(RULES).unwrap();
+        let external = ExternalParameters::new("2021-07-10T12:00:00Z", "NL", &ValueSets::builtin());
+
+        let results = validate_rules(&rules, &vaccination(2), &external);
+        assert_eq!(
+            outcomes(&results),
+            vec![RuleOutcome::Passed, RuleOutcome::Passed]
+        );
+
+        let results = validate_rules(&rules, &vaccination(1), &external);
+        assert_eq!(
+            outcomes(&results),
+            vec![RuleOutcome::Failed, RuleOutcome::Passed]
+        );
+    }
+
+    #[test]
+    fn it_fails_too_early() {
+        let rules = parse_rules(RULES).unwrap();
+        let external = ExternalParameters::new("2021-06-10T12:00:00Z", "NL", &ValueSets::builtin());
+
+        let results = validate_rules(&rules, &vaccination(2), &external);
+        assert_eq!(results[1].outcome, RuleOutcome::Failed);
+    }
+
+    fn store() -> RuleStore {
+        let rule = |identifier: &str, version: &str, from: &str, region: &str, kind: &str| {
+            json!({
+                "Identifier": identifier, "Type": "Acceptance", "Country": "NL",
+                "Region": region, "Version": version, "CertificateType": kind,
+                "ValidFrom": from, "ValidTo": "2030-06-01T00:00:00Z", "Logic": version
+            })
+        };
+
+        let mut store = RuleStore::new();
+        store
+            .add_rules(
+                &json!([
+                    rule("GR-NL-0001", "1.0.0", "2021-07-01T00:00:00Z", "", "General"),
+                    rule("GR-NL-0001", "1.1.0", "2021-09-01T00:00:00Z", "", "General"),
+                    rule(
+                        "GR-NL-0001",
+                        "1.10.0",
+                        "2022-01-01T00:00:00Z",
+                        "",
+                        "General"
+                    ),
+                    rule("TR-NL-0001", "1.0.0", "2021-07-01T00:00:00Z", "", "Test"),
+                    rule(
+                        "VR-NL-0001",
+                        "1.0.0",
+                        "2021-07-01T00:00:00Z",
+                        "BES",
+                        "Vaccination"
+                    ),
+                ])
+                .to_string(),
+            )
+            .unwrap();
+        store
+    }
+
+    fn selected(store: &RuleStore, date: &str, region: Option<&str>) -> Vec<String> {
+        let date_time = parse_date_time(date).unwrap();
+        store
+            .select(&date_time, "NL", region, CertificateType::Vaccination, None)
+            .iter()
+            .map(|rule| format!("{} {}", rule.identifier, rule.version))
+            .collect()
+    }
+
+    #[test]
+    fn it_selects_the_version_valid_at_a_date() {
+        let store = store();
+
+        assert_eq!(store.len(), 5);
+        assert!(selected(&store, "2021-06-01T00:00:00Z", None).is_empty());
+        assert_eq!(
+            selected(&store, "2021-08-01T00:00:00Z", None),
+            vec!["GR-NL-0001 1.0.0"]
+        );
+        assert_eq!(
+            selected(&store, "2022-02-01T00:00:00Z", None),
+            vec!["GR-NL-0001 1.10.0"]
+        );
+    }
+
+    #[test]
+    fn it_selects_by_region_and_certificate_type() {
+        let store = store();
+
+        assert_eq!(
+            selected(&store, "2021-08-01T00:00:00Z", Some("BES")),
+            vec!["GR-NL-0001 1.0.0", "VR-NL-0001 1.0.0"]
+        );
+
+        let date_time = parse_date_time("2021-08-01T00:00:00Z").unwrap();
+        assert!(store
+            .select(&date_time, "DE", None, CertificateType::Vaccination, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn it_selects_by_rule_type() {
+        let rule = |identifier: &str, rule_type: &str, country: &str| {
+            json!({
+                "Identifier": identifier, "Type": rule_type, "Country": country,
+                "Version": "1.0.0", "CertificateType": "General",
+                "ValidFrom": "2021-07-01T00:00:00Z", "ValidTo": "2030-06-01T00:00:00Z",
+                "Logic": true
+            })
+        };
+        let mut store = RuleStore::new();
+        store
+            .add_rules(
+                &json!([
+                    rule("GR-NL-0001", "Acceptance", "NL"),
+                    rule("IR-DE-0001", "Invalidation", "DE"),
+                ])
+                .to_string(),
+            )
+            .unwrap();
+        let date_time = parse_date_time("2021-08-01T00:00:00Z").unwrap();
+        let selected = |country: &str, issuer_country: Option<&str>| -> Vec<String> {
+            store
+                .select(
+                    &date_time,
+                    country,
+                    None,
+                    CertificateType::Test,
+                    issuer_country,
+                )
+                .iter()
+                .map(|rule| rule.identifier.clone())
+                .collect()
+        };
+
+        assert_eq!(selected("NL", Some("DE")), vec!["GR-NL-0001", "IR-DE-0001"]);
+        assert_eq!(selected("NL", Some("NL")), vec!["GR-NL-0001"]);
+        assert_eq!(selected("DE", None), Vec::<String>::new());
+
+        let external = ExternalParameters {
+            issuer_country_code: String::from("DE"),
+            ..ExternalParameters::new("2021-08-01T00:00:00Z", "BE", &ValueSets::new())
+        };
+        let results = store.validate(&vaccination(2), &external).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].identifier, "IR-DE-0001");
+    }
+
+    #[test]
+    fn it_validates_with_the_selected_rules() {
+        let external = ExternalParameters::new("2021-08-01T00:00:00Z", "NL", &ValueSets::new());
+        let results = store().validate(&vaccination(2), &external).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].identifier, "GR-NL-0001");
+        assert_eq!(results[0].outcome, RuleOutcome::Passed);
+    }
+
+    #[test]
+    fn error_in_logic_is_open() {
+        let mut rules = parse_rules(RULES).unwrap();
+        rules[0].logic = json!({"unknown": []});
+        let external = ExternalParameters::new("2021-07-10T12:00:00Z", "NL", &ValueSets::new());
+
+        let results = validate_rules(&rules, &vaccination(2), &external);
+        assert_eq!(results[0].outcome, RuleOutcome::Open);
+        assert_eq!(
+            results[0].error,
+            Some(String::from("Unknown operator unknown"))
+        );
+    }
+
+    #[test]
+    fn external_parameters_of_claims() {
+        let claims = CwtClaims {
+            issuer: Some(String::from("DE")),
+            issued_at: Some(1625875200),
+            expiration: None,
+            ..CwtClaims::default()
+        };
+        let external = ExternalParameters::of_claims(1628208000, "NL", &ValueSets::new(), &claims);
+
+        assert_eq!(external.validation_clock, "2021-08-06T00:00:00Z");
+        assert_eq!(external.iat, "2021-07-10T00:00:00Z");
+        assert_eq!(external.exp, "");
+        assert_eq!(external.issuer_country_code, "DE");
+    }
+}
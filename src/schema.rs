@@ -0,0 +1,381 @@
+use crate::schema_version::{is_date, is_partial_date};
+use ciborium::value::Value;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fmt;
+
+///
+/// A violation of the eu-dcc-schema, addressed by the path
+/// of the field in the health certificate, e.g. `v[0].dt`
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+///
+/// Format of a string field
+///
+#[derive(Clone, Copy)]
+enum Format {
+    /// Any text
+    Text,
+    /// A complete date YYYY-MM-DD
+    Date,
+    /// A date and time according to RFC 3339
+    DateTime,
+    /// A complete or partial date, or empty
+    DateOfBirth,
+    /// Standardised name, capitals and fillers
+    Standardised,
+    /// Schema version major.minor.patch
+    Version,
+    /// Two letter country code
+    Country,
+}
+
+///
+/// Type of a field
+///
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Text with a maximum length
+    Text(Format, usize),
+    /// Integer in a range
+    Integer(u64, u64),
+}
+
+/// A field of an object with its kind and whether it's required
+struct Field(&'static str, Kind, bool);
+
+const PERSON_NAME: &[Field] = &[
+    Field("fn", Kind::Text(Format::Text, 80), false),
+    Field("fnt", Kind::Text(Format::Standardised, 80), true),
+    Field("gn", Kind::Text(Format::Text, 80), false),
+    Field("gnt", Kind::Text(Format::Standardised, 80), false),
+];
+
+const VACCINATION_ENTRY: &[Field] = &[
+    Field("tg", Kind::Text(Format::Text, 80), true),
+    Field("vp", Kind::Text(Format::Text, 80), true),
+    Field("mp", Kind::Text(Format::Text, 80), true),
+    Field("ma", Kind::Text(Format::Text, 80), true),
+    Field("dn", Kind::Integer(1, 9), true),
+    Field("sd", Kind::Integer(1, 9), true),
+    Field("dt", Kind::Text(Format::Date, 10), true),
+    Field("co", Kind::Text(Format::Country, 2), true),
+    Field("is", Kind::Text(Format::Text, 80), true),
+    Field("ci", Kind::Text(Format::Text, 80), true),
+];
+
+const TEST_ENTRY: &[Field] = &[
+    Field("tg", Kind::Text(Format::Text, 80), true),
+    Field("tt", Kind::Text(Format::Text, 80), true),
+    Field("nm", Kind::Text(Format::Text, 80), false),
+    Field("ma", Kind::Text(Format::Text, 80), false),
+    Field("sc", Kind::Text(Format::DateTime, 80), true),
+    Field("dr", Kind::Text(Format::DateTime, 80), false),
+    Field("tr", Kind::Text(Format::Text, 80), true),
+    Field("tc", Kind::Text(Format::Text, 80), false),
+    Field("co", Kind::Text(Format::Country, 2), true),
+    Field("is", Kind::Text(Format::Text, 80), true),
+    Field("ci", Kind::Text(Format::Text, 80), true),
+];
+
+const RECOVERY_ENTRY: &[Field] = &[
+    Field("tg", Kind::Text(Format::Text, 80), true),
+    Field("fr", Kind::Text(Format::Date, 10), true),
+    Field("co", Kind::Text(Format::Country, 2), true),
+    Field("is", Kind::Text(Format::Text, 80), true),
+    Field("df", Kind::Text(Format::Date, 10), true),
+    Field("du", Kind::Text(Format::Date, 10), true),
+    Field("ci", Kind::Text(Format::Text, 80), true),
+];
+
+///
+/// Validate the health certificate against the rules of the eu-dcc-schema
+///
+/// https://github.com/ehn-dcc-development/eu-dcc-schema
+///
+/// The rules are embedded, so no network is needed. All violations are
+/// returned, an empty list means the health certificate conforms to the schema.
+///
+pub fn validate_schema(hcert: &Value) -> Vec<SchemaViolation> {
+    let mut validator = Validator::default();
+    validator.certificate(hcert);
+    validator.violations
+}
+
+#[derive(Default)]
+struct Validator {
+    violations: Vec<SchemaViolation>,
+}
+
+impl Validator {
+    fn violation(&mut self, path: &str, message: &str) {
+        self.violations.push(SchemaViolation {
+            path: String::from(path),
+            message: String::from(message),
+        });
+    }
+
+    fn certificate(&mut self, hcert: &Value) {
+        let map = match hcert.as_map() {
+            Some(map) => map,
+            None => return self.violation("$", "is not an object"),
+        };
+
+        for (key, value) in map {
+            match key.as_text() {
+                Some("ver") => self.field("ver", value, Kind::Text(Format::Version, 11)),
+                Some("dob") => self.field("dob", value, Kind::Text(Format::DateOfBirth, 10)),
+                Some("nam") => self.object("nam", value, PERSON_NAME),
+                Some(group @ ("v" | "t" | "r")) => self.group(group, value),
+                Some(key) => self.violation(key, "is not allowed"),
+                None => self.violation("$", "has a key which is not a string"),
+            }
+        }
+
+        for required in ["ver", "nam", "dob"] {
+            if lookup(map, required).is_none() {
+                self.violation(required, "is required");
+            }
+        }
+
+        let groups = ["v", "t", "r"]
+            .iter()
+            .filter(|group| lookup(map, group).is_some())
+            .count();
+        if groups != 1 {
+            self.violation(
+                "$",
+                "must contain exactly one vaccination (v), test (t) or recovery (r) group",
+            );
+        }
+    }
+
+    fn group(&mut self, group: &str, value: &Value) {
+        let entries = match value.as_array() {
+            Some(entries) => entries,
+            None => return self.violation(group, "is not an array"),
+        };
+
+        if entries.len() != 1 {
+            self.violation(group, "must contain exactly one entry");
+        }
+
+        let fields = match group {
+            "v" => VACCINATION_ENTRY,
+            "t" => TEST_ENTRY,
+            _ => RECOVERY_ENTRY,
+        };
+
+        for (i, entry) in entries.iter().enumerate() {
+            self.object(&format!("{}[{}]", group, i), entry, fields);
+        }
+    }
+
+    fn object(&mut self, path: &str, value: &Value, fields: &[Field]) {
+        let map = match value.as_map() {
+            Some(map) => map,
+            None => return self.violation(path, "is not an object"),
+        };
+
+        for (key, value) in map {
+            let key = key.as_text().unwrap_or_default();
+            match fields.iter().find(|field| field.0 == key) {
+                Some(field) => self.field(&format!("{}.{}", path, key), value, field.1),
+                None => self.violation(&format!("{}.{}", path, key), "is not allowed"),
+            }
+        }
+
+        for field in fields.iter().filter(|field| field.2) {
+            if lookup(map, field.0).is_none() {
+                self.violation(&format!("{}.{}", path, field.0), "is required");
+            }
+        }
+    }
+
+    fn field(&mut self, path: &str, value: &Value, kind: Kind) {
+        match (kind, value) {
+            (Kind::Text(format, max_length), Value::Text(text)) => {
+                if text.chars().count() > max_length {
+                    self.violation(path, &format!("is longer than {} characters", max_length));
+                }
+                if !format.matches(text) {
+                    self.violation(path, &format!("is not {}", format));
+                }
+            }
+            (Kind::Text(..), _) => self.violation(path, "is not a string"),
+            (Kind::Integer(min, max), Value::Integer(integer)) => match u64::try_from(*integer) {
+                Ok(integer) if integer >= min && integer <= max => {}
+                _ => self.violation(path, &format!("is not between {} and {}", min, max)),
+            },
+            (Kind::Integer(..), _) => self.violation(path, "is not an integer"),
+        }
+    }
+}
+
+impl Format {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Format::Text => true,
+            Format::Date => is_date(text),
+            Format::DateTime => is_date_time(text),
+            Format::DateOfBirth => text.is_empty() || is_date(text) || is_partial_date(text),
+            Format::Standardised => text.chars().all(|c| c.is_ascii_uppercase() || c == '<'),
+            Format::Version => {
+                let parts: Vec<&str> = text.split('.').collect();
+                parts.len() == 3
+                    && parts
+                        .iter()
+                        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+            }
+            Format::Country => text.len() == 2 && text.chars().all(|c| c.is_ascii_uppercase()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Format::Text => "a string",
+            Format::Date => "a date (YYYY-MM-DD)",
+            Format::DateTime => "a date and time (RFC 3339)",
+            Format::DateOfBirth => "a date of birth (YYYY-MM-DD, YYYY-MM, YYYY or empty)",
+            Format::Standardised => "a standardised name (A-Z and <)",
+            Format::Version => "a version (major.minor.patch)",
+            Format::Country => "a country code (ISO 3166-1 alpha-2)",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+///
+/// Find the value of a key in a map
+///
+fn lookup<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|entry| entry.0.as_text() == Some(key))
+        .map(|entry| &entry.1)
+}
+
+///
+/// A date and time according to RFC 3339, e.g. 2021-05-01T10:00:00Z
+/// or 2021-05-01T10:00:00.123+02:00
+///
+pub(crate) fn is_date_time(date_time: &str) -> bool {
+    let (date, time) = match date_time.split_once(['T', 't']) {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let (time, offset) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, None)
+    } else if let Some(index) = time.rfind(['+', '-']) {
+        (&time[..index], Some(&time[index + 1..]))
+    } else {
+        return false;
+    };
+
+    let time = match time.split_once('.') {
+        Some((time, fraction)) if is_digits(fraction) => time,
+        Some(_) => return false,
+        None => time,
+    };
+
+    let valid_offset = match offset {
+        Some(offset) => is_clock(offset, 2),
+        None => true,
+    };
+
+    is_date(date) && is_clock(time, 3) && valid_offset
+}
+
+///
+/// A time of parts of two digits, separated by a colon
+///
+fn is_clock(time: &str, parts: usize) -> bool {
+    let split: Vec<&str> = time.split(':').collect();
+    split.len() == parts && split.iter().all(|part| part.len() == 2 && is_digits(part))
+}
+
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claims::CwtClaims;
+    use crate::read_dcc::read_dcc;
+
+    const TEST_DCC: &str = "6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1";
+
+    fn hcert(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn paths(violations: &[SchemaViolation]) -> Vec<&str> {
+        violations
+            .iter()
+            .map(|violation| violation.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_dcc_conforms() {
+        let claims = CwtClaims::try_from(read_dcc(TEST_DCC).unwrap().payload().unwrap()).unwrap();
+        assert_eq!(validate_schema(&claims.hcert), vec![])
+    }
+
+    #[test]
+    fn it_reports_the_path_of_a_wrong_date() {
+        let violations = validate_schema(&hcert(
+            r#"{"ver": "1.3.0", "nam": {"fnt": "DOE"}, "dob": "1990",
+            "r": [{"tg": "840539006", "fr": "10-01-2021", "co": "NL", "is": "Ministry",
+            "df": "2021-01-20", "du": "2021-07-10", "ci": "URN:UVCI:01:NL:123"}]}"#,
+        ));
+
+        assert_eq!(paths(&violations), vec!["r[0].fr"]);
+        assert_eq!(
+            violations[0].to_string(),
+            "r[0].fr: is not a date (YYYY-MM-DD)"
+        );
+    }
+
+    #[test]
+    fn it_reports_missing_and_extra_fields() {
+        let violations = validate_schema(&hcert(
+            r#"{"ver": "1.3.0", "nam": {"fnt": "DOE", "nick": "J"}, "dob": "1990",
+            "r": [{"tg": "840539006", "fr": "2021-01-10", "co": "NL", "is": "Ministry",
+            "df": "2021-01-20", "du": "2021-07-10"}]}"#,
+        ));
+
+        assert_eq!(paths(&violations), vec!["nam.nick", "r[0].ci"]);
+    }
+
+    #[test]
+    fn it_requires_exactly_one_group() {
+        let violations = validate_schema(&hcert(
+            r#"{"ver": "1.3.0", "nam": {"fnt": "DOE"}, "dob": "1990"}"#,
+        ));
+
+        assert_eq!(paths(&violations), vec!["$"]);
+    }
+
+    #[test]
+    fn it_checks_dates_and_times() {
+        assert!(is_date_time("2021-05-01T10:00:00Z"));
+        assert!(is_date_time("2021-05-01T10:00:00.123+02:00"));
+        assert!(!is_date_time("2021-05-01 10:00:00"));
+        assert!(!is_date_time("2021-05-01T10:00Z"));
+    }
+}
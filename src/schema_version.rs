@@ -0,0 +1,229 @@
+use crate::hcert::HealthCertificate;
+use std::fmt;
+
+///
+/// Version of the eu-dcc-schema, read from the `ver` field
+/// of the health certificate
+///
+/// https://github.com/ehn-dcc-development/eu-dcc-schema/releases
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+/// The oldest supported schema version
+pub const OLDEST_SUPPORTED: SchemaVersion = SchemaVersion::new(1, 0, 0);
+
+/// The newest supported schema version, patches are accepted
+pub const NEWEST_SUPPORTED: SchemaVersion = SchemaVersion::new(1, 3, 0);
+
+impl SchemaVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> SchemaVersion {
+        SchemaVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse a version in the format major.minor.patch
+    pub fn parse(version: &str) -> Result<SchemaVersion, String> {
+        let parts = version
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>();
+
+        match parts.as_deref() {
+            Ok([major, minor, patch]) => Ok(SchemaVersion::new(*major, *minor, *patch)),
+            _ => Err(format!("Invalid schema version {}", version)),
+        }
+    }
+
+    /// Whether the version is 1.0.0 up to and including 1.3.x
+    pub fn is_supported(&self) -> bool {
+        *self >= OLDEST_SUPPORTED
+            && (self.major, self.minor) <= (NEWEST_SUPPORTED.major, NEWEST_SUPPORTED.minor)
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+///
+/// Check the health certificate against the rules which differ
+/// between schema versions
+///
+/// Older versions are accepted, the deviations from the rules of the
+/// detected version are returned. Fails when the version can't be read.
+///
+pub fn check_schema_version(
+    health_certificate: &HealthCertificate,
+) -> Result<(SchemaVersion, Vec<String>), String> {
+    let version = SchemaVersion::parse(&health_certificate.ver)?;
+    let mut deviations = Vec::new();
+
+    if !version.is_supported() {
+        deviations.push(format!(
+            "Schema version {} is not supported, expected {} up to {}.{}.x",
+            version, OLDEST_SUPPORTED, NEWEST_SUPPORTED.major, NEWEST_SUPPORTED.minor
+        ));
+    }
+
+    // Up to 1.1.0 the date of birth is a complete date, later
+    // versions also allow a year, a year and month or empty
+    let dob = &health_certificate.dob;
+    if version < SchemaVersion::new(1, 1, 0) {
+        if !is_date(dob) {
+            deviations.push(format!(
+                "dob {} is not a date as required by schema version {}",
+                dob, version
+            ));
+        }
+    } else if !(dob.is_empty() || is_date(dob) || is_partial_date(dob)) {
+        deviations.push(format!(
+            "dob {} is not a (partial) date as required by schema version {}",
+            dob, version
+        ));
+    }
+
+    for (i, test) in health_certificate.t.iter().flatten().enumerate() {
+        // The date of the test result is removed in 1.3.0
+        if test.dr.is_some() && version >= SchemaVersion::new(1, 3, 0) {
+            deviations.push(format!(
+                "t[{}].dr is not part of schema version {}",
+                i, version
+            ));
+        }
+
+        // The testing centre is optional since 1.3.0
+        if test.tc.is_none() && version < SchemaVersion::new(1, 3, 0) {
+            deviations.push(format!(
+                "t[{}].tc is required by schema version {}",
+                i, version
+            ));
+        }
+    }
+
+    Ok((version, deviations))
+}
+
+///
+/// A complete date YYYY-MM-DD
+///
+pub(crate) fn is_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    parts.len() == 3 && is_digits(parts[0], 4) && is_digits(parts[1], 2) && is_digits(parts[2], 2)
+}
+
+///
+/// A year YYYY or a year and month YYYY-MM
+///
+pub(crate) fn is_partial_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    match parts.as_slice() {
+        [year] => is_digits(year, 4),
+        [year, month] => is_digits(year, 4) && is_digits(month, 2),
+        _ => false,
+    }
+}
+
+fn is_digits(value: &str, length: usize) -> bool {
+    value.len() == length && value.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcert::{PersonName, TestEntry};
+
+    fn health_certificate(ver: &str, dob: &str) -> HealthCertificate {
+        HealthCertificate {
+            ver: String::from(ver),
+            nam: PersonName {
+                r#fn: None,
+                fnt: String::from("DOE"),
+                gn: None,
+                gnt: None,
+            },
+            dob: String::from(dob),
+            v: None,
+            t: None,
+            r: None,
+        }
+    }
+
+    fn test_entry(dr: Option<&str>, tc: Option<&str>) -> TestEntry {
+        TestEntry {
+            tg: String::from("840539006"),
+            tt: String::from("LP6464-4"),
+            nm: None,
+            ma: None,
+            sc: String::from("2021-05-01T10:00:00Z"),
+            dr: dr.map(String::from),
+            tr: String::from("260415000"),
+            tc: tc.map(String::from),
+            co: String::from("NL"),
+            is: String::from("Ministry"),
+            ci: String::from("URN:UVCI:01:NL:123"),
+        }
+    }
+
+    #[test]
+    fn it_parses_the_version() {
+        assert_eq!(
+            SchemaVersion::parse("1.3.0"),
+            Ok(SchemaVersion::new(1, 3, 0))
+        );
+        assert!(SchemaVersion::parse("1.3").is_err());
+        assert!(SchemaVersion::parse("v1.3.0").is_err());
+    }
+
+    #[test]
+    fn it_supports_1_0_up_to_1_3() {
+        assert!(SchemaVersion::new(1, 0, 0).is_supported());
+        assert!(SchemaVersion::new(1, 3, 2).is_supported());
+        assert!(!SchemaVersion::new(1, 4, 0).is_supported());
+        assert!(!SchemaVersion::new(0, 9, 0).is_supported());
+    }
+
+    #[test]
+    fn partial_dob_is_allowed_since_1_1() {
+        let (_, deviations) = check_schema_version(&health_certificate("1.3.0", "1964")).unwrap();
+        assert!(deviations.is_empty());
+
+        let (_, deviations) = check_schema_version(&health_certificate("1.0.0", "1964")).unwrap();
+        assert_eq!(deviations.len(), 1);
+    }
+
+    #[test]
+    fn test_fields_depend_on_version() {
+        let mut hc = health_certificate("1.0.0", "1964-08-12");
+        hc.t = Some(vec![test_entry(Some("2021-05-01T12:00:00Z"), None)]);
+        let (_, deviations) = check_schema_version(&hc).unwrap();
+        assert_eq!(
+            deviations,
+            vec!["t[0].tc is required by schema version 1.0.0"]
+        );
+
+        hc.ver = String::from("1.3.0");
+        let (_, deviations) = check_schema_version(&hc).unwrap();
+        assert_eq!(
+            deviations,
+            vec!["t[0].dr is not part of schema version 1.3.0"]
+        );
+    }
+
+    #[test]
+    fn unsupported_version_is_deviation() {
+        let (version, deviations) =
+            check_schema_version(&health_certificate("2.0.0", "1964-08-12")).unwrap();
+        assert_eq!(version, SchemaVersion::new(2, 0, 0));
+        assert_eq!(deviations.len(), 1);
+    }
+}
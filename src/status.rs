@@ -0,0 +1,224 @@
+use crate::error::ErrorCode;
+use crate::rules::{RuleOutcome, RuleResult};
+use crate::validity::Validity;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+///
+/// Overall status of a DCC, computed from all steps
+///
+/// The first step that fails determines the status: parsing,
+/// verifying the signature, revocation, validity in time and
+/// at last the business rules.
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    /// All checks passed
+    Valid,
+    /// The DCC is parsed, but can't be accepted, e.g. the
+    /// certificate of the issuer can't be read or the DCC has
+    /// no valid health certificate
+    Invalid,
+    /// The expiration is in the past
+    Expired,
+    /// The DCC is in a revocation list
+    Revoked,
+    /// The signature doesn't match the data
+    SignatureInvalid,
+    /// The issuer is not trusted
+    UnknownIssuer,
+    /// The data can't be decoded
+    NotParseable,
+    /// A business rule failed or couldn't be evaluated
+    RulesFailed,
+}
+
+impl Status {
+    /// Name of the status, e.g. SIGNATURE_INVALID
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Status::Valid => "VALID",
+            Status::Invalid => "INVALID",
+            Status::Expired => "EXPIRED",
+            Status::Revoked => "REVOKED",
+            Status::SignatureInvalid => "SIGNATURE_INVALID",
+            Status::UnknownIssuer => "UNKNOWN_ISSUER",
+            Status::NotParseable => "NOT_PARSEABLE",
+            Status::RulesFailed => "RULES_FAILED",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+///
+/// Compute the overall status from the results of the steps
+///
+/// The error code is the first error while parsing or verifying. Without
+/// an error, the DCC is only valid when parsed with a health certificate,
+/// the signature is valid, it's valid in time and all rules are passed.
+/// The rule results are none when rules are set, but couldn't be evaluated.
+///
+pub fn overall_status(
+    error_code: ErrorCode,
+    successful: bool,
+    health_certificate: bool,
+    signature_valid: bool,
+    validity: Validity,
+    rule_results: Option<&[RuleResult]>,
+) -> Status {
+    match error_code {
+        ErrorCode::Base45
+        | ErrorCode::Zlib
+        | ErrorCode::Cbor
+        | ErrorCode::CoseStructure
+        | ErrorCode::DetachedPayload
+        | ErrorCode::Payload => return Status::NotParseable,
+        ErrorCode::UnknownKid | ErrorCode::UntrustedCertificate => return Status::UnknownIssuer,
+        ErrorCode::BadSignature => return Status::SignatureInvalid,
+        ErrorCode::BadCertificate | ErrorCode::BadKey | ErrorCode::UnsupportedAlgorithm => {
+            return Status::Invalid
+        }
+        ErrorCode::Revoked => return Status::Revoked,
+        ErrorCode::Expired => return Status::Expired,
+        ErrorCode::NotYetValid | ErrorCode::WrongCertificateType | ErrorCode::WrongCountry => {
+            return Status::Invalid
+        }
+        ErrorCode::None => {}
+    }
+
+    if !successful {
+        return Status::NotParseable;
+    }
+
+    if !health_certificate || !signature_valid || validity != Validity::Valid {
+        return Status::Invalid;
+    }
+
+    match rule_results {
+        Some(results)
+            if results
+                .iter()
+                .all(|result| result.outcome == RuleOutcome::Passed) =>
+        {
+            Status::Valid
+        }
+        _ => Status::RulesFailed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_result(outcome: RuleOutcome) -> RuleResult {
+        RuleResult {
+            identifier: String::from("VR-NL-0001"),
+            version: String::from("1.0.0"),
+            country: String::from("NL"),
+            description: String::new(),
+            outcome,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn it_is_valid_when_all_checks_pass() {
+        let results = [rule_result(RuleOutcome::Passed)];
+        let status = overall_status(
+            ErrorCode::None,
+            true,
+            true,
+            true,
+            Validity::Valid,
+            Some(&results),
+        );
+
+        assert_eq!(status, Status::Valid);
+    }
+
+    #[test]
+    fn errors_determine_the_status() {
+        let status = |code| overall_status(code, true, true, false, Validity::Valid, Some(&[]));
+
+        assert_eq!(status(ErrorCode::Zlib), Status::NotParseable);
+        assert_eq!(status(ErrorCode::UnknownKid), Status::UnknownIssuer);
+        assert_eq!(status(ErrorCode::BadSignature), Status::SignatureInvalid);
+        assert_eq!(status(ErrorCode::BadKey), Status::Invalid);
+        assert_eq!(status(ErrorCode::Revoked), Status::Revoked);
+        assert_eq!(status(ErrorCode::Expired), Status::Expired);
+        assert_eq!(status(ErrorCode::WrongCertificateType), Status::Invalid);
+        assert_eq!(status(ErrorCode::WrongCountry), Status::Invalid);
+    }
+
+    #[test]
+    fn rules_are_checked_last() {
+        let results = [rule_result(RuleOutcome::Open)];
+
+        assert_eq!(
+            overall_status(
+                ErrorCode::None,
+                true,
+                true,
+                true,
+                Validity::Valid,
+                Some(&results)
+            ),
+            Status::RulesFailed
+        );
+        assert_eq!(
+            overall_status(
+                ErrorCode::Expired,
+                true,
+                true,
+                true,
+                Validity::Expired,
+                Some(&results)
+            ),
+            Status::Expired
+        );
+    }
+
+    #[test]
+    fn rules_which_are_not_evaluated_fail() {
+        let status = overall_status(ErrorCode::None, true, true, true, Validity::Valid, None);
+
+        assert_eq!(status, Status::RulesFailed);
+    }
+
+    #[test]
+    fn it_is_invalid_without_health_certificate() {
+        let status = overall_status(
+            ErrorCode::None,
+            true,
+            false,
+            true,
+            Validity::Valid,
+            Some(&[]),
+        );
+
+        assert_eq!(status, Status::Invalid);
+    }
+
+    #[test]
+    fn it_is_not_parseable_by_default() {
+        let status = overall_status(
+            ErrorCode::None,
+            false,
+            false,
+            false,
+            Validity::Unknown,
+            Some(&[]),
+        );
+
+        assert_eq!(status, Status::NotParseable);
+        assert_eq!(status.to_string(), "NOT_PARSEABLE");
+    }
+}
@@ -0,0 +1,385 @@
+use crate::claims::CwtClaims;
+use crate::cose::CoseSingleSigned;
+use crate::from_js_value;
+use crate::hcert::HealthCertificate;
+use crate::issue::{write_dcc, Issuer};
+use crate::verify::{certificate_kid, EKU_RECOVERY, EKU_TEST, EKU_VACCINATION};
+use p256::ecdsa::{DerSignature, SigningKey};
+use p256::elliptic_curve::rand_core::OsRng;
+use p256::pkcs8::EncodePrivateKey;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::Duration;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+use x509_cert::der::asn1::GeneralizedTime;
+use x509_cert::der::oid::ObjectIdentifier;
+use x509_cert::der::Encode;
+use x509_cert::ext::pkix::ExtendedKeyUsage;
+use x509_cert::name::Name;
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+use x509_cert::time::{Time, Validity};
+
+/// Seconds in a day
+const DAY: i64 = 24 * 60 * 60;
+
+/// The vaccination certificate of the generated DCCs in the tests
+#[cfg(test)]
+pub(crate) const HEALTH_CERTIFICATE_JSON: &str = r#"{"ver": "1.3.0",
+    "nam": {"fnt": "DOE", "gnt": "JANE"}, "dob": "1990-01-01",
+    "v": [{"tg": "840539006", "vp": "1119349007", "mp": "EU/1/20/1528",
+    "ma": "ORG-100030215", "dn": 2, "sd": 2, "dt": "2021-06-01",
+    "co": "NL", "is": "Ministry", "ci": "URN:UVCI:01:NL:123"}]}"#;
+
+///
+/// The kind of test DCC to generate
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixtureKind {
+    /// Signed by the DSC and valid for a year
+    Valid,
+    /// Signed by the DSC, but expired yesterday
+    Expired,
+    /// The payload is changed after signing, the signature is invalid
+    Tampered,
+    /// Signed by a DSC without the extended key usage for the certificate type
+    WrongEku,
+    /// The issuer claim doesn't match the country of the DSC
+    WrongCountry,
+}
+
+///
+/// A generated DCC and the trust list entry of the DSC which signed it
+///
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    /// The DCC, prefixed with HC1:
+    pub dcc: String,
+
+    /// Key identifier of the DSC
+    pub kid: String,
+
+    /// The DSC, base64 encoded DER
+    pub certificate: String,
+}
+
+/// TestDataGenerator
+///
+/// Generates DCCs signed by throwaway document signer certificates (DSC),
+/// for tests which verify the signature. The keys are generated when the
+/// generator is created and never leave it. Only available with the
+/// `test-data` feature.
+///
+/// The DSCs are self-signed P-256 certificates for the country, valid from
+/// a day before until two years after `now`, in seconds since epoch.
+///
+#[wasm_bindgen]
+pub struct TestDataGenerator {
+    country: String,
+    now: i64,
+    dsc: Dsc,
+}
+
+#[wasm_bindgen]
+impl TestDataGenerator {
+    /// A generator for the country, e.g. NL
+    #[wasm_bindgen(constructor)]
+    pub fn new(country: &str, now: f64) -> Result<TestDataGenerator, String> {
+        let now = now as i64;
+
+        Ok(TestDataGenerator {
+            country: country.to_string(),
+            now,
+            dsc: Dsc::new(country, &[EKU_TEST, EKU_VACCINATION, EKU_RECOVERY], now)?,
+        })
+    }
+
+    /// Generate a DCC of the kind for the health certificate
+    #[wasm_bindgen(js_name = fixture)]
+    pub fn fixture_js(
+        &self,
+        kind: FixtureKind,
+        health_certificate: JsValue,
+    ) -> Result<Fixture, String> {
+        self.fixture(kind, &from_js_value(&health_certificate)?)
+    }
+}
+
+impl TestDataGenerator {
+    /// Generate a DCC of the kind for the health certificate
+    pub fn fixture(
+        &self,
+        kind: FixtureKind,
+        health_certificate: &HealthCertificate,
+    ) -> Result<Fixture, String> {
+        let valid_claims = CwtClaims::new(
+            &self.country,
+            self.now,
+            self.now + 365 * DAY,
+            health_certificate,
+        )?;
+
+        match kind {
+            FixtureKind::Valid => self.dsc.fixture(&valid_claims),
+            FixtureKind::Expired => self.dsc.fixture(&CwtClaims {
+                issued_at: Some(self.now - 365 * DAY),
+                expiration: Some(self.now - DAY),
+                ..valid_claims
+            }),
+            FixtureKind::Tampered => {
+                let signed = self.dsc.issuer()?.sign(&valid_claims)?;
+
+                // Extend the expiration, but keep the original signature
+                let tampered_claims = CwtClaims {
+                    expiration: valid_claims.expiration.map(|exp| exp + 365 * DAY),
+                    ..valid_claims
+                };
+                let mut tampered =
+                    CoseSingleSigned::new(-7, &self.dsc.kid_bytes(), &tampered_claims.to_cbor())?;
+                tampered.set_signature(signed.signature().to_vec());
+
+                Ok(self.dsc.entry(write_dcc(&tampered)?))
+            }
+            FixtureKind::WrongEku => {
+                let required = match eku_of(health_certificate) {
+                    Some(eku) => eku,
+                    None => return Err(String::from("Health certificate has no entry")),
+                };
+                let others: Vec<&str> = [EKU_TEST, EKU_VACCINATION, EKU_RECOVERY]
+                    .iter()
+                    .copied()
+                    .filter(|eku| *eku != required)
+                    .collect();
+
+                Dsc::new(&self.country, &others, self.now)?.fixture(&valid_claims)
+            }
+            FixtureKind::WrongCountry => {
+                let other = if self.country == "XX" { "XY" } else { "XX" };
+                self.dsc.fixture(&CwtClaims {
+                    issuer: Some(String::from(other)),
+                    ..valid_claims
+                })
+            }
+        }
+    }
+}
+
+///
+/// A throwaway document signer certificate and its private key
+///
+struct Dsc {
+    key: SigningKey,
+    certificate: Vec<u8>,
+}
+
+impl Dsc {
+    /// A self-signed DSC with the extended key usages
+    fn new(country: &str, ekus: &[&str], now: i64) -> Result<Dsc, String> {
+        let key = SigningKey::random(&mut OsRng);
+
+        let subject = Name::from_str(&format!("CN=Test DSC {},C={}", country, country))
+            .map_err(|e| format!("Invalid country {}: {}", country, e))?;
+        let validity = Validity {
+            not_before: time(now - DAY)?,
+            not_after: time(now + 2 * 365 * DAY)?,
+        };
+        let public_key = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key())
+            .map_err(|e| format!("Error on encoding public key: {}", e))?;
+
+        let mut builder = CertificateBuilder::new(
+            Profile::Leaf {
+                issuer: subject.clone(),
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            },
+            SerialNumber::from(1u32),
+            validity,
+            subject,
+            public_key,
+            &key,
+        )
+        .map_err(|e| format!("Error on creating DSC: {}", e))?;
+
+        let ekus = ekus
+            .iter()
+            .map(|eku| ObjectIdentifier::new_unwrap(eku))
+            .collect();
+        builder
+            .add_extension(&ExtendedKeyUsage(ekus))
+            .map_err(|e| format!("Error on adding extended key usage: {}", e))?;
+
+        let certificate = builder
+            .build::<DerSignature>()
+            .and_then(|certificate| Ok(certificate.to_der()?))
+            .map_err(|e| format!("Error on signing DSC: {}", e))?;
+
+        Ok(Dsc { key, certificate })
+    }
+
+    fn kid(&self) -> String {
+        certificate_kid(&self.certificate)
+    }
+
+    fn kid_bytes(&self) -> Vec<u8> {
+        base64::decode(self.kid()).unwrap_or_default()
+    }
+
+    /// Issuer signing with the private key of the DSC
+    fn issuer(&self) -> Result<Issuer, String> {
+        let key = self
+            .key
+            .to_pkcs8_der()
+            .map_err(|e| format!("Error on encoding private key: {}", e))?;
+
+        Issuer::es256(key.as_bytes(), &self.kid())
+    }
+
+    /// The claims signed by the DSC
+    fn fixture(&self, claims: &CwtClaims) -> Result<Fixture, String> {
+        Ok(self.entry(self.issuer()?.issue(claims)?))
+    }
+
+    /// The DCC with the trust list entry of the DSC
+    fn entry(&self, dcc: String) -> Fixture {
+        Fixture {
+            dcc,
+            kid: self.kid(),
+            certificate: base64::encode(&self.certificate),
+        }
+    }
+}
+
+/// Time of a certificate validity, seconds since epoch
+fn time(seconds: i64) -> Result<Time, String> {
+    let seconds = u64::try_from(seconds).map_err(|_| String::from("Time before epoch"))?;
+
+    match GeneralizedTime::from_unix_duration(Duration::from_secs(seconds)) {
+        Ok(time) => Ok(Time::GeneralTime(time)),
+        Err(e) => Err(format!("Invalid time: {}", e)),
+    }
+}
+
+/// Extended key usage needed to sign the health certificate
+fn eku_of(health_certificate: &HealthCertificate) -> Option<&'static str> {
+    if health_certificate.v.is_some() {
+        Some(EKU_VACCINATION)
+    } else if health_certificate.t.is_some() {
+        Some(EKU_TEST)
+    } else if health_certificate.r.is_some() {
+        Some(EKU_RECOVERY)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoded_dcc::DecodedDcc;
+    use crate::error::ErrorCode;
+    use crate::parse_options::ParseOptions;
+    use crate::read_dcc::read_dcc;
+    use crate::status::Status;
+    use crate::trust_store::TrustStore;
+    use crate::verifier::DccVerifier;
+    use crate::verify::{verify_signature_with_keys, KeyCache};
+    use x509_parser::prelude::*;
+
+    const NOW: f64 = 1622316073.0;
+
+    fn health_certificate() -> HealthCertificate {
+        serde_json::from_str(HEALTH_CERTIFICATE_JSON).unwrap()
+    }
+
+    fn fixture(kind: FixtureKind) -> Fixture {
+        TestDataGenerator::new("NL", NOW)
+            .unwrap()
+            .fixture(kind, &health_certificate())
+            .unwrap()
+    }
+
+    /// Verify the signature with the trust list entry of the fixture
+    fn verify(fixture: &Fixture) -> Result<CwtClaims, String> {
+        let mut trust_store = TrustStore::new();
+        trust_store.add_certificate(&fixture.kid, &fixture.certificate);
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+
+        let cose = read_dcc(&fixture.dcc)?;
+        verify_signature_with_keys(&cose, &options, &mut KeyCache::new(), &mut Vec::new())?;
+        CwtClaims::try_from(cose.payload().unwrap())
+    }
+
+    /// Decode and verify the fixture with the trust list entry of the fixture
+    fn decode(fixture: &Fixture) -> DecodedDcc {
+        let mut trust_store = TrustStore::new();
+        trust_store.add_certificate(&fixture.kid, &fixture.certificate);
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+        options.set_now(NOW);
+
+        DccVerifier::with_options(options).verify(&fixture.dcc)
+    }
+
+    /// The extended key usages of the DSC of a fixture
+    fn ekus(fixture: &Fixture) -> Vec<String> {
+        let der = base64::decode(&fixture.certificate).unwrap();
+        let (_, certificate) = X509Certificate::from_der(&der).unwrap();
+
+        match certificate.tbs_certificate.extended_key_usage() {
+            Some((_, eku)) => eku.other.iter().map(|oid| oid.to_id_string()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn valid_fixture_verifies() {
+        let fixture = fixture(FixtureKind::Valid);
+        let claims = verify(&fixture).unwrap();
+
+        assert!(fixture.dcc.starts_with("HC1:"));
+        assert_eq!(claims.issuer, Some(String::from("NL")));
+        assert_eq!(claims.health_certificate().unwrap(), health_certificate());
+        assert!(ekus(&fixture).contains(&String::from(EKU_VACCINATION)));
+        assert_eq!(decode(&fixture).status, Status::Valid);
+    }
+
+    #[test]
+    fn expired_fixture_is_expired() {
+        let claims = verify(&fixture(FixtureKind::Expired)).unwrap();
+        assert!(claims.expiration.unwrap() < NOW as i64);
+    }
+
+    #[test]
+    fn tampered_fixture_doesnt_verify() {
+        assert!(verify(&fixture(FixtureKind::Tampered)).is_err());
+    }
+
+    #[test]
+    fn wrong_eku_fixture_misses_the_certificate_type() {
+        let fixture = fixture(FixtureKind::WrongEku);
+
+        assert!(verify(&fixture).is_ok());
+        assert!(!ekus(&fixture).contains(&String::from(EKU_VACCINATION)));
+        assert!(ekus(&fixture).contains(&String::from(EKU_TEST)));
+
+        let decoded = decode(&fixture);
+        assert_eq!(decoded.error_code, ErrorCode::WrongCertificateType);
+        assert_eq!(decoded.status, Status::Invalid);
+    }
+
+    #[test]
+    fn wrong_country_fixture_has_another_issuer() {
+        let fixture = fixture(FixtureKind::WrongCountry);
+        let claims = verify(&fixture).unwrap();
+        assert_eq!(claims.issuer, Some(String::from("XX")));
+
+        let decoded = decode(&fixture);
+        assert_eq!(decoded.error_code, ErrorCode::WrongCountry);
+        assert_eq!(decoded.status, Status::Invalid);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::certificates::find_issuer_cert;
+use serde_json::Value;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// TrustStore
+///
+/// The certificates trusted to sign a Digital Covid Certificate. There
+/// are two kinds of certificates in the store:
+///
+/// 1) Document Signer Certificates (DSC)
+/// These are looked up by kid and used to verify the signature
+///
+/// 2) Country Signing Certificate Authorities (CSCA)
+/// A certificate embedded in the x5chain header of a DCC is trusted
+/// when it is issued by one of these certificates
+///
+/// All certificates are base64 encoded DER.
+///
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct TrustStore {
+    builtin: bool,
+    certificates: HashMap<String, String>,
+    cscas: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl TrustStore {
+    /// An empty TrustStore
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TrustStore {
+        TrustStore {
+            builtin: false,
+            certificates: HashMap::new(),
+            cscas: Vec::new(),
+        }
+    }
+
+    /// A TrustStore containing the certificates shipped with this package
+    pub fn builtin() -> TrustStore {
+        TrustStore {
+            builtin: true,
+            ..TrustStore::new()
+        }
+    }
+
+    /// Add a document signer certificate for the given kid
+    pub fn add_certificate(&mut self, kid: &str, certificate: &str) {
+        self.certificates
+            .insert(kid.to_string(), certificate.to_string());
+    }
+
+    /// Add a country signing certificate authority
+    pub fn add_csca(&mut self, certificate: &str) {
+        self.cscas.push(certificate.to_string());
+    }
+
+    ///
+    /// Add the certificates of a trust list in JSON
+    ///
+    /// Either a list in the format of the DGC gateway, objects with the
+    /// `kid`, `rawData` and optionally `certificateType` (DSC or CSCA),
+    /// or an object with the certificates by kid.
+    ///
+    pub fn add_trust_list(&mut self, json: &str) -> Result<(), String> {
+        let trust_list: Value = match serde_json::from_str(json) {
+            Ok(trust_list) => trust_list,
+            Err(e) => return Err(format!("Invalid trust list: {}", e)),
+        };
+
+        match trust_list {
+            Value::Array(entries) => {
+                for entry in &entries {
+                    let field = |name| entry.get(name).and_then(Value::as_str);
+
+                    match (field("certificateType"), field("kid"), field("rawData")) {
+                        (Some("CSCA"), _, Some(raw_data)) => self.add_csca(raw_data),
+                        (None | Some("DSC"), Some(kid), Some(raw_data)) => {
+                            self.add_certificate(kid, raw_data)
+                        }
+                        (Some(_), _, Some(_)) => {}
+                        _ => return Err(format!("Invalid trust list entry: {}", entry)),
+                    }
+                }
+            }
+            Value::Object(certificates) => {
+                for (kid, certificate) in &certificates {
+                    match certificate.as_str() {
+                        Some(certificate) => self.add_certificate(kid, certificate),
+                        None => return Err(format!("Invalid certificate for kid {}", kid)),
+                    }
+                }
+            }
+            _ => return Err(String::from("Trust list is not a list or an object")),
+        }
+
+        Ok(())
+    }
+}
+
+impl TrustStore {
+    /// Find the document signer certificate for a kid, certificates added
+    /// to the store take precedence over the builtin certificates
+    pub fn find_certificate(&self, kid: &str) -> Option<String> {
+        match self.certificates.get(kid) {
+            Some(certificate) => Some(certificate.clone()),
+            None if self.builtin => find_issuer_cert(kid),
+            None => None,
+        }
+    }
+
+    /// The country signing certificate authorities
+    pub fn cscas(&self) -> &[String] {
+        &self.cscas
+    }
+}
+
+impl Default for TrustStore {
+    fn default() -> Self {
+        TrustStore::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_store_is_empty() {
+        assert_eq!(TrustStore::new().find_certificate("vvYa1vaWkGg="), None)
+    }
+
+    #[test]
+    fn builtin_store_finds_a_cert() {
+        assert!(TrustStore::builtin()
+            .find_certificate("vvYa1vaWkGg=")
+            .is_some())
+    }
+
+    #[test]
+    fn added_certificate_takes_precedence() {
+        let mut store = TrustStore::builtin();
+        store.add_certificate("vvYa1vaWkGg=", "some_cert");
+
+        assert_eq!(
+            store.find_certificate("vvYa1vaWkGg="),
+            Some(String::from("some_cert"))
+        )
+    }
+
+    #[test]
+    fn it_adds_a_csca() {
+        let mut store = TrustStore::new();
+        store.add_csca("some_csca");
+
+        assert_eq!(store.cscas(), &[String::from("some_csca")])
+    }
+
+    #[test]
+    fn it_adds_a_trust_list() {
+        let mut store = TrustStore::new();
+        store
+            .add_trust_list(
+                r#"[
+                    {"kid": "DEsVUSvpFAE=", "certificateType": "DSC", "rawData": "some_cert"},
+                    {"kid": "AAAAAAAAAAA=", "certificateType": "CSCA", "rawData": "some_csca"},
+                    {"kid": "BBBBBBBBBBB=", "certificateType": "UPLOAD", "rawData": "other"}
+                ]"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.find_certificate("DEsVUSvpFAE="),
+            Some(String::from("some_cert"))
+        );
+        assert_eq!(store.cscas(), &[String::from("some_csca")]);
+        assert_eq!(store.find_certificate("BBBBBBBBBBB="), None);
+    }
+
+    #[test]
+    fn it_adds_certificates_by_kid() {
+        let mut store = TrustStore::new();
+        store
+            .add_trust_list(r#"{"DEsVUSvpFAE=": "some_cert"}"#)
+            .unwrap();
+
+        assert!(store.find_certificate("DEsVUSvpFAE=").is_some());
+        assert!(store.add_trust_list("[1]").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use serde::Serialize;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Prefix of a UVCI as uniform resource name
+const PREFIX: &str = "URN:UVCI:";
+
+/// Characters of the Luhn mod N checksum in ASCII order, N = 38
+const CHECKSUM_ALPHABET: &str = "/0123456789:ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+///
+/// Unique Vaccination Certificate/assertion Identifier (UVCI)
+///
+/// https://ec.europa.eu/health/sites/default/files/ehealth/docs/vaccination-proof_interoperability-guidelines_en.pdf
+///
+/// The certificate identifier (ci) of a DCC, e.g.
+/// `URN:UVCI:01:NL:187/37512422923` or `URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W`
+///
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Uvci {
+    /// Version of the UVCI schema, e.g. 01
+    pub version: String,
+
+    /// Country code of the issuer
+    pub country: String,
+
+    /// Issuing entity, the first fragment when the identifier has more
+    pub issuing_entity: Option<String>,
+
+    /// Opaque unique identifier, the last fragment
+    pub opaque_id: String,
+
+    /// All fragments after the country, e.g. the issuing entity,
+    /// vaccine or other structured data and the opaque identifier
+    pub fragments: Vec<String>,
+
+    /// Luhn mod N checksum character
+    pub checksum: Option<String>,
+
+    /// Whether the checksum is valid, none when the identifier has
+    /// no checksum
+    pub valid: Option<bool>,
+}
+
+impl TryFrom<&str> for Uvci {
+    type Error = String;
+
+    fn try_from(uvci: &str) -> Result<Self, Self::Error> {
+        let identifier = uvci.strip_prefix(PREFIX).unwrap_or(uvci);
+
+        if !identifier
+            .chars()
+            .all(|c| CHECKSUM_ALPHABET.contains(c) || c == '#')
+        {
+            return Err(format!("UVCI {} contains invalid characters", uvci));
+        }
+
+        let (identifier, checksum) = match identifier.split_once('#') {
+            Some((identifier, checksum)) if checksum.len() == 1 => (identifier, Some(checksum)),
+            Some(_) => return Err(format!("UVCI {} has an invalid checksum", uvci)),
+            None => (identifier, None),
+        };
+
+        // The version is followed by the country, optionally separated
+        let version = identifier.get(..2).unwrap_or_default();
+        if version.len() != 2 || !version.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("UVCI {} has an invalid version", uvci));
+        }
+        let rest = identifier[2..].trim_start_matches([':', '/']);
+
+        let country = rest.get(..2).unwrap_or_default();
+        if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("UVCI {} has an invalid country", uvci));
+        }
+
+        let fragments: Vec<String> = rest[2..]
+            .split(['/', ':'])
+            .filter(|fragment| !fragment.is_empty())
+            .map(String::from)
+            .collect();
+
+        let (issuing_entity, opaque_id) = match fragments.as_slice() {
+            [] => return Err(format!("UVCI {} has no unique identifier", uvci)),
+            [opaque_id] => (None, opaque_id.clone()),
+            [issuing_entity, .., opaque_id] => (Some(issuing_entity.clone()), opaque_id.clone()),
+        };
+
+        let valid =
+            checksum.map(|checksum| checksum == luhn_mod_n(&format!("{}{}", PREFIX, identifier)));
+
+        Ok(Uvci {
+            version: version.to_string(),
+            country: country.to_string(),
+            issuing_entity,
+            opaque_id,
+            fragments,
+            checksum: checksum.map(String::from),
+            valid,
+        })
+    }
+}
+
+///
+/// Parse a UVCI and validate its checksum
+///
+#[wasm_bindgen]
+pub fn parse_uvci(uvci: &str) -> Result<Uvci, String> {
+    Uvci::try_from(uvci)
+}
+
+///
+/// Luhn mod N checksum character over the characters /, 0-9, : and A-Z
+///
+/// As in annex 2 of the eHealth Network guidelines, the code points are
+/// the positions of the characters in ASCII order. The checksum of a
+/// UVCI is calculated over the identifier including the URN:UVCI: prefix.
+///
+pub fn luhn_mod_n(input: &str) -> String {
+    let alphabet: Vec<char> = CHECKSUM_ALPHABET.chars().collect();
+    let n = alphabet.len();
+
+    let mut factor = 2;
+    let mut sum = 0;
+    for c in input.chars().rev() {
+        let code_point = alphabet.iter().position(|&a| a == c).unwrap_or(0);
+        let addend = factor * code_point;
+        factor = if factor == 2 { 1 } else { 2 };
+        sum += addend / n + addend % n;
+    }
+
+    alphabet[(n - sum % n) % n].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_an_identifier_with_issuing_entity() {
+        let uvci = Uvci::try_from("URN:UVCI:01:NL:187/37512422923").unwrap();
+
+        assert_eq!(uvci.version, "01");
+        assert_eq!(uvci.country, "NL");
+        assert_eq!(uvci.issuing_entity, Some(String::from("187")));
+        assert_eq!(uvci.opaque_id, "37512422923");
+        assert_eq!(uvci.fragments, vec!["187", "37512422923"]);
+        assert_eq!(uvci.checksum, None);
+        assert_eq!(uvci.valid, None);
+    }
+
+    #[test]
+    fn it_keeps_all_fragments() {
+        let uvci = Uvci::try_from("URN:UVCI:01:NL:A:B/C:123").unwrap();
+
+        assert_eq!(uvci.issuing_entity, Some(String::from("A")));
+        assert_eq!(uvci.opaque_id, "123");
+        assert_eq!(uvci.fragments, vec!["A", "B", "C", "123"]);
+    }
+
+    #[test]
+    fn it_parses_an_opaque_identifier_with_checksum() {
+        let uvci = Uvci::try_from("URN:UVCI:01DE/5CWLU12RNOB9RXSEOP6FG8#W").unwrap();
+
+        assert_eq!(uvci.country, "DE");
+        assert_eq!(uvci.issuing_entity, None);
+        assert_eq!(uvci.opaque_id, "5CWLU12RNOB9RXSEOP6FG8");
+        assert_eq!(uvci.checksum, Some(String::from("W")));
+
+        // The checksum of this German test certificate, also in the dgc-testdata,
+        // isn't calculated as in annex 2
+        assert_eq!(uvci.valid, Some(false));
+    }
+
+    #[test]
+    fn it_validates_the_checksum_of_issued_identifiers() {
+        // Identifiers of San Marino in the dgc-testdata
+        for uvci in &[
+            "URN:UVCI:01:SM:112#N",
+            "URN:UVCI:01:SM:115#H",
+            "URN:UVCI:01:SM:117#D",
+            "URN:UVCI:01:SM:121#O",
+            "URN:UVCI:01:SM:122#M",
+        ] {
+            assert_eq!(Uvci::try_from(*uvci).unwrap().valid, Some(true), "{}", uvci);
+        }
+
+        assert_eq!(luhn_mod_n("URN:UVCI:01:SM:112"), "N");
+
+        // The checksum is calculated with prefix
+        let valid = Uvci::try_from("01:SM:112#N").unwrap();
+        assert_eq!(valid.valid, Some(true));
+
+        let invalid = Uvci::try_from("URN:UVCI:01:SM:113#N").unwrap();
+        assert_eq!(invalid.valid, Some(false));
+    }
+
+    #[test]
+    fn invalid_identifiers_are_error() {
+        assert!(Uvci::try_from("URN:UVCI:01:nl:187/123").is_err());
+        assert!(Uvci::try_from("URN:UVCI:1:NL:187/123").is_err());
+        assert!(Uvci::try_from("URN:UVCI:01:NL").is_err());
+        assert!(Uvci::try_from("URN:UVCI:01:NL:123#AB").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::claims::CwtClaims;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+///
+/// Validity of a DCC in time, determined by the
+/// expiration and issued at claims
+///
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Validity {
+    /// The claims couldn't be read or have no expiration
+    Unknown,
+    /// Valid at the moment of verification
+    Valid,
+    /// The expiration is in the past
+    Expired,
+    /// The DCC is issued in the future
+    NotYetValid,
+}
+
+///
+/// Check the validity of the claims at a moment in time
+///
+/// Both now and the clock skew are in seconds. The clock skew
+/// is the tolerated difference between the clock of the issuer
+/// and the verifier. Without expiration the validity is unknown.
+///
+pub fn check_validity(claims: &CwtClaims, now: i64, clock_skew: i64) -> Validity {
+    if let Some(issued_at) = claims.issued_at {
+        if issued_at > now.saturating_add(clock_skew) {
+            return Validity::NotYetValid;
+        }
+    }
+
+    match claims.expiration {
+        Some(expiration) if expiration <= now.saturating_sub(clock_skew) => Validity::Expired,
+        Some(_expiration) => Validity::Valid,
+        None => Validity::Unknown,
+    }
+}
+
+///
+/// Seconds since epoch of the javascript clock
+///
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
+}
+
+///
+/// Seconds since epoch of the system clock
+///
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(_e) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(issued_at: i64, expiration: i64) -> CwtClaims {
+        CwtClaims {
+            issued_at: Some(issued_at),
+            expiration: Some(expiration),
+            ..CwtClaims::default()
+        }
+    }
+
+    #[test]
+    fn it_is_valid() {
+        assert_eq!(check_validity(&claims(100, 200), 150, 0), Validity::Valid)
+    }
+
+    #[test]
+    fn it_is_expired() {
+        assert_eq!(check_validity(&claims(100, 200), 200, 0), Validity::Expired)
+    }
+
+    #[test]
+    fn it_is_not_yet_valid() {
+        assert_eq!(
+            check_validity(&claims(100, 200), 99, 0),
+            Validity::NotYetValid
+        )
+    }
+
+    #[test]
+    fn it_tolerates_clock_skew() {
+        assert_eq!(check_validity(&claims(100, 200), 90, 10), Validity::Valid);
+        assert_eq!(check_validity(&claims(100, 200), 209, 10), Validity::Valid);
+    }
+
+    #[test]
+    fn extreme_moments_do_not_overflow() {
+        assert_eq!(
+            check_validity(&claims(100, 200), i64::MAX, 10),
+            Validity::Expired
+        );
+        assert_eq!(
+            check_validity(&claims(100, 200), i64::MIN, 10),
+            Validity::NotYetValid
+        );
+    }
+
+    #[test]
+    fn it_is_unknown_without_expiration() {
+        assert_eq!(
+            check_validity(&CwtClaims::default(), 150, 0),
+            Validity::Unknown
+        );
+
+        let issued_in_future = CwtClaims {
+            issued_at: Some(200),
+            ..CwtClaims::default()
+        };
+        assert_eq!(
+            check_validity(&issued_in_future, 150, 0),
+            Validity::NotYetValid
+        )
+    }
+
+    #[test]
+    fn system_clock_is_after_2021() {
+        assert!(now() > 1609459200)
+    }
+}
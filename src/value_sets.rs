@@ -0,0 +1,281 @@
+use crate::hcert::HealthCertificate;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Value set of the targeted disease or agent (tg)
+pub const DISEASE_AGENT_TARGETED: &str = "disease-agent-targeted";
+/// Value set of the vaccine or prophylaxis (vp)
+pub const VACCINE_PROPHYLAXIS: &str = "sct-vaccines-covid-19";
+/// Value set of the vaccine medicinal product (mp)
+pub const VACCINE_MEDICINAL_PRODUCT: &str = "vaccines-covid-19-names";
+/// Value set of the vaccine marketing authorization holder or manufacturer (ma)
+pub const VACCINE_MANUFACTURER: &str = "vaccines-covid-19-auth-holders";
+/// Value set of the type of test (tt)
+pub const TEST_TYPE: &str = "covid-19-lab-test-type";
+/// Value set of the test result (tr)
+pub const TEST_RESULT: &str = "covid-19-lab-result";
+/// Value set of the rapid antigen test device (ma)
+pub const TEST_MANUFACTURER: &str = "covid-19-lab-test-manufacturer-and-name";
+
+/// The value sets shipped with this package
+const BUILTIN: &[&str] = &[
+    include_str!("value_sets/disease-agent-targeted.json"),
+    include_str!("value_sets/vaccine-prophylaxis.json"),
+    include_str!("value_sets/vaccine-medicinal-product.json"),
+    include_str!("value_sets/vaccine-mah-manf.json"),
+    include_str!("value_sets/test-type.json"),
+    include_str!("value_sets/test-result.json"),
+    include_str!("value_sets/test-manf.json"),
+];
+
+///
+/// A value set in the format of the EU value sets
+///
+/// https://github.com/ehn-dcc-development/ehn-dcc-valuesets
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueSet {
+    pub value_set_id: String,
+    pub value_set_date: String,
+    pub value_set_values: HashMap<String, ValueSetEntry>,
+}
+
+///
+/// A code in a value set
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ValueSetEntry {
+    pub display: String,
+    #[serde(default)]
+    pub lang: String,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub system: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// The builtin value sets, parsed once
+static BUILTIN_VALUE_SETS: OnceLock<ValueSets> = OnceLock::new();
+
+/// ValueSets
+///
+/// The value sets used to resolve the codes in a health certificate
+/// into a human readable display, e.g. `840539006` into `COVID-19`.
+/// A value set added to the store replaces the value set with the
+/// same id. The value sets are shared between clones.
+///
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct ValueSets {
+    value_sets: HashMap<String, Arc<ValueSet>>,
+}
+
+#[wasm_bindgen]
+impl ValueSets {
+    /// Empty ValueSets
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ValueSets {
+        ValueSets {
+            value_sets: HashMap::new(),
+        }
+    }
+
+    ///
+    /// The value sets shipped with this package
+    ///
+    /// They are parsed on the first call and shared afterwards.
+    /// Parsing can't fail, the builtin value sets are tested to be valid.
+    ///
+    pub fn builtin() -> ValueSets {
+        BUILTIN_VALUE_SETS
+            .get_or_init(|| {
+                let mut value_sets = ValueSets::new();
+                for json in BUILTIN {
+                    value_sets
+                        .add_value_set(json)
+                        .expect("unreachable: builtin value sets are valid");
+                }
+                value_sets
+            })
+            .clone()
+    }
+
+    /// Add a value set in the JSON format of the EU value sets
+    pub fn add_value_set(&mut self, json: &str) -> Result<(), String> {
+        let value_set: ValueSet = match serde_json::from_str(json) {
+            Ok(value_set) => value_set,
+            Err(e) => return Err(format!("Invalid value set: {}", e)),
+        };
+
+        self.value_sets
+            .insert(value_set.value_set_id.clone(), Arc::new(value_set));
+        Ok(())
+    }
+
+    /// The display of a code in a value set
+    pub fn display(&self, value_set_id: &str, code: &str) -> Option<String> {
+        self.value_sets
+            .get(value_set_id)?
+            .value_set_values
+            .get(code)
+            .map(|entry| entry.display.clone())
+    }
+}
+
+impl ValueSets {
+    /// A value set by id
+    pub fn get(&self, value_set_id: &str) -> Option<&ValueSet> {
+        self.value_sets
+            .get(value_set_id)
+            .map(|value_set| &**value_set)
+    }
+
+    /// The codes of each value set, by value set id
+    pub fn codes(&self) -> HashMap<String, Vec<String>> {
+        self.value_sets
+            .iter()
+            .map(|(id, value_set)| {
+                let mut codes: Vec<String> = value_set.value_set_values.keys().cloned().collect();
+                codes.sort();
+                (id.clone(), codes)
+            })
+            .collect()
+    }
+
+    /// All value sets
+    pub fn iter(&self) -> impl Iterator<Item = &ValueSet> {
+        self.value_sets.values().map(|value_set| &**value_set)
+    }
+
+    ///
+    /// A display ready copy of the health certificate, in which the codes
+    /// are replaced by their display. Codes which are not in a value set
+    /// remain unchanged.
+    ///
+    pub fn resolve(&self, health_certificate: &HealthCertificate) -> JsonValue {
+        let mut resolved = serde_json::to_value(health_certificate).unwrap_or(JsonValue::Null);
+
+        let groups: [(&str, &[(&str, &str)]); 3] = [
+            (
+                "v",
+                &[
+                    ("tg", DISEASE_AGENT_TARGETED),
+                    ("vp", VACCINE_PROPHYLAXIS),
+                    ("mp", VACCINE_MEDICINAL_PRODUCT),
+                    ("ma", VACCINE_MANUFACTURER),
+                ],
+            ),
+            (
+                "t",
+                &[
+                    ("tg", DISEASE_AGENT_TARGETED),
+                    ("tt", TEST_TYPE),
+                    ("tr", TEST_RESULT),
+                    ("ma", TEST_MANUFACTURER),
+                ],
+            ),
+            ("r", &[("tg", DISEASE_AGENT_TARGETED)]),
+        ];
+
+        for (group, fields) in groups.iter() {
+            let entries = match resolved.get_mut(*group).and_then(|g| g.as_array_mut()) {
+                Some(entries) => entries,
+                None => continue,
+            };
+
+            for entry in entries.iter_mut() {
+                for (field, value_set_id) in fields.iter() {
+                    if let Some(value) = entry.get_mut(*field) {
+                        let display = value
+                            .as_str()
+                            .and_then(|code| self.display(value_set_id, code));
+                        if let Some(display) = display {
+                            *value = JsonValue::String(display);
+                        }
+                    }
+                }
+            }
+        }
+
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_value_sets_are_loaded() {
+        let value_sets = ValueSets::builtin();
+
+        assert_eq!(value_sets.iter().count(), BUILTIN.len());
+        assert_eq!(
+            value_sets.display(DISEASE_AGENT_TARGETED, "840539006"),
+            Some(String::from("COVID-19"))
+        );
+        assert_eq!(
+            value_sets.display(VACCINE_MEDICINAL_PRODUCT, "EU/1/20/1528"),
+            Some(String::from("Comirnaty"))
+        );
+    }
+
+    #[test]
+    fn builtin_value_sets_are_parsed_once() {
+        let value_sets = ValueSets::builtin();
+        let other = ValueSets::builtin();
+
+        assert!(Arc::ptr_eq(
+            &value_sets.value_sets[TEST_RESULT],
+            &other.value_sets[TEST_RESULT]
+        ));
+    }
+
+    #[test]
+    fn added_value_set_replaces_builtin() {
+        let mut value_sets = ValueSets::builtin();
+        value_sets
+            .add_value_set(
+                r#"{"valueSetId": "covid-19-lab-result", "valueSetDate": "2021-04-27",
+                "valueSetValues": {"260415000": {"display": "Niet gedetecteerd"}}}"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            value_sets.display(TEST_RESULT, "260415000"),
+            Some(String::from("Niet gedetecteerd"))
+        );
+        assert_eq!(value_sets.display(TEST_RESULT, "260373001"), None);
+    }
+
+    #[test]
+    fn invalid_value_set_is_error() {
+        assert!(ValueSets::new().add_value_set("{}").is_err())
+    }
+
+    #[test]
+    fn it_resolves_the_codes() {
+        let hcert: HealthCertificate = serde_json::from_str(
+            r#"{"ver": "1.3.0", "nam": {"fnt": "DOE"}, "dob": "1990",
+            "v": [{"tg": "840539006", "vp": "1119349007", "mp": "EU/1/20/1528",
+            "ma": "ORG-100030215", "dn": 2, "sd": 2, "dt": "2021-06-01",
+            "co": "NL", "is": "Ministry", "ci": "URN:UVCI:01:NL:123"}]}"#,
+        )
+        .unwrap();
+
+        let resolved = ValueSets::builtin().resolve(&hcert);
+        let vaccination = &resolved["v"][0];
+
+        assert_eq!(vaccination["tg"], "COVID-19");
+        assert_eq!(vaccination["vp"], "SARS-CoV2 mRNA vaccine");
+        assert_eq!(vaccination["mp"], "Comirnaty");
+        assert_eq!(vaccination["ma"], "Biontech Manufacturing GmbH");
+        assert_eq!(vaccination["co"], "NL");
+    }
+}
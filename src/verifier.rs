@@ -0,0 +1,452 @@
+use crate::batch::BatchResult;
+use crate::claims::CwtClaims;
+use crate::decoded_dcc::{DecodedDcc, DecodedDccBuilder};
+use crate::error::{DccError, ErrorCode};
+use crate::parse_options::ParseOptions;
+use crate::read_dcc::read_dcc;
+use crate::revocation::check_revocation;
+use crate::rules::ExternalParameters;
+use crate::schema::validate_schema;
+use crate::schema_version::check_schema_version;
+use crate::uvci::Uvci;
+use crate::validity::{check_validity, Validity};
+use crate::verify::{
+    signing_certificate, verify_certificate_usage, verify_signature_with_keys, KeyCache,
+};
+use ciborium::value::Value;
+use std::convert::TryFrom;
+
+///
+/// Decode and verify Digital Covid Certificates in native Rust
+///
+/// The same steps as `parse_with_options`, but the result is a
+/// DecodedDcc without javascript values, which can be serialized
+/// with serde.
+///
+#[derive(Clone, Default)]
+pub struct DccVerifier {
+    options: ParseOptions,
+}
+
+impl DccVerifier {
+    /// A verifier with the default options
+    pub fn new() -> DccVerifier {
+        DccVerifier::with_options(ParseOptions::new())
+    }
+
+    /// A verifier with options, e.g. the trusted certificates
+    pub fn with_options(options: ParseOptions) -> DccVerifier {
+        DccVerifier { options }
+    }
+
+    /// The options of the verifier
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Decode and verify a DCC, starting with HC1:
+    pub fn verify(&self, dcc_certificate: &str) -> DecodedDcc {
+        decode_and_verify(dcc_certificate, &self.options, &mut KeyCache::new())
+    }
+
+    ///
+    /// Decode and verify many DCCs
+    ///
+    /// The certificates of the trust store are loaded once for all
+    /// DCCs. Returns the result of each DCC, in the same order, and
+    /// the counts per status, issuer country and kid.
+    ///
+    pub fn verify_batch<S: AsRef<str>>(&self, dcc_certificates: &[S]) -> BatchResult {
+        let mut keys = KeyCache::new();
+
+        BatchResult::new(
+            dcc_certificates
+                .iter()
+                .map(|dcc| decode_and_verify(dcc.as_ref(), &self.options, &mut keys))
+                .collect(),
+        )
+    }
+}
+
+///
+/// Decode a DCC and verify the signature, revocation, validity
+/// in time and business rules with the options. The public keys of
+/// the certificates are taken from the cache when already loaded.
+///
+pub(crate) fn decode_and_verify(
+    dcc_certificate: &str,
+    options: &ParseOptions,
+    keys: &mut KeyCache,
+) -> DecodedDcc {
+    let result_builder = DecodedDccBuilder::new();
+
+    // base45 decode, zlib inflate, into cose
+    let mut cose = match read_dcc(dcc_certificate) {
+        Ok(cose) => cose,
+        Err(e) => {
+            return result_builder
+                .fail_with_error(&e.context("Couldn't parse COSE message"))
+                .build()
+        }
+    };
+
+    // A detached payload is provided separately
+    if cose.is_detached() {
+        let attached = match options.detached_payload() {
+            Some(payload) => cose.attach_payload(payload),
+            None => Err(String::from("no payload provided")),
+        };
+
+        if let Err(e) = attached {
+            return result_builder
+                .fail_with_error(&DccError::new(
+                    ErrorCode::DetachedPayload,
+                    &format!("Couldn't attach detached payload: {}", e),
+                ))
+                .build();
+        }
+    }
+
+    // Only a cbor encoded payload has claims, the signature is verified over the raw bytes
+    let mut warnings = Vec::new();
+    let payload = match cose.payload() {
+        Some(payload) => payload.clone(),
+        None => {
+            warnings.push(String::from("The payload is not cbor encoded"));
+            Value::Null
+        }
+    };
+
+    // Parse cbor payload into json
+    let json = match serde_json::to_value(&payload) {
+        Ok(json) => json,
+        Err(_e) => {
+            return result_builder
+                .fail_with_error(&DccError::new(
+                    ErrorCode::Payload,
+                    "Error on converting the payload to JSON",
+                ))
+                .build()
+        }
+    };
+
+    // Typed CWT claims, the raw data remains available for unknown claims
+    let (claims, validity) = match CwtClaims::try_from(&payload) {
+        Ok(claims) => {
+            // Check if expired or not yet valid
+            let validity = check_validity(&claims, options.now(), options.clock_skew());
+            if claims.expiration.is_none() {
+                warnings.push(String::from("The DCC has no expiration claim"));
+            }
+            (claims, validity)
+        }
+        Err(e) => {
+            warnings.push(format!("Invalid CWT claims: {}", e));
+            (CwtClaims::default(), Validity::Unknown)
+        }
+    };
+
+    // Typed health certificate according to the eu-dcc-schema
+    let mut health_certificate = None;
+    let mut display = None;
+    let mut schema_version = None;
+    let mut uvci = None;
+    let mut rule_results = Vec::new();
+    let mut rules_evaluated = options.rule_store().is_none();
+    if claims.hcert.is_null() {
+        warnings.push(String::from("The DCC has no health certificate"));
+    } else {
+        match claims.health_certificate() {
+            Ok(hc) => {
+                // Detect the schema version and its deviations
+                match check_schema_version(&hc) {
+                    Ok(version) => schema_version = Some(version),
+                    Err(e) => warnings.push(e),
+                }
+
+                // Parse the certificate identifier and validate its checksum
+                if let Some(ci) = hc.certificate_identifier() {
+                    match Uvci::try_from(ci) {
+                        Ok(parsed) => uvci = Some(parsed),
+                        Err(e) => warnings.push(e),
+                    }
+                }
+
+                // Resolve the codes with the value sets
+                display = Some(options.value_sets().resolve(&hc));
+
+                // Validate the business rules of the country of arrival
+                if let Some(rule_store) = options.rule_store() {
+                    let external = ExternalParameters::of_claims(
+                        options.now(),
+                        options.country_code(),
+                        options.value_sets(),
+                        &claims,
+                    );
+                    match serde_json::to_value(&hc)
+                        .map_err(|e| e.to_string())
+                        .and_then(|hc| rule_store.validate(&hc, &external))
+                    {
+                        Ok(results) => {
+                            rule_results = results;
+                            rules_evaluated = true;
+                        }
+                        Err(e) => warnings.push(format!("Couldn't validate the rules: {}", e)),
+                    }
+                }
+
+                health_certificate = Some(hc);
+            }
+            Err(e) => warnings.push(e),
+        }
+    }
+
+    let kid = cose.kid().unwrap_or_default();
+    let alg = cose.alg().unwrap_or(0);
+
+    // Optional validation against the eu-dcc-schema
+    let result_builder = if options.validate_schema() && !claims.hcert.is_null() {
+        result_builder.schema_violations(&validate_schema(&claims.hcert))
+    } else {
+        result_builder
+    };
+
+    // Parsed successfully
+    let result_builder = match uvci {
+        Some(uvci) => result_builder.uvci(uvci),
+        None => result_builder,
+    };
+    let result_builder = match schema_version {
+        Some((version, deviations)) => result_builder.schema_version(&version, &deviations),
+        None => result_builder,
+    };
+    let result_builder = match health_certificate {
+        Some(hc) => result_builder.health_certificate(hc),
+        None => result_builder,
+    };
+    let result_builder = match display {
+        Some(display) => result_builder.display(display),
+        None => result_builder,
+    };
+    let result_builder = if rules_evaluated {
+        result_builder
+    } else {
+        result_builder.rules_not_evaluated()
+    };
+    let parsed_successful = result_builder
+        .success()
+        .kid(&kid)
+        .alg(alg)
+        .data(json)
+        .claims(claims.clone())
+        .rule_results(&rule_results)
+        .validity(validity);
+
+    // Verify the signature with the trusted certificates
+    let verified = verify_signature_with_keys(&cose, options, keys, &mut warnings);
+    let parsed_successful = parsed_successful.warnings(&warnings);
+
+    if let Err(e) = verified {
+        return parsed_successful.signature_error(&e).build();
+    }
+    let parsed_successful = parsed_successful.signature_valid(true);
+
+    // The DSC must be allowed to sign for the country and type of certificate
+    if let Some(certificate) = signing_certificate(&cose, options.trust_store()) {
+        if let Err(e) = verify_certificate_usage(&certificate, &claims) {
+            return parsed_successful.verification_error(&e).build();
+        }
+    }
+
+    // Look up the hashes of the verified certificate in the revocation lists
+    if let Some(hash_type) = check_revocation(&cose, &claims, options.revocation_store()) {
+        return parsed_successful.revoked(hash_type).build();
+    }
+
+    match validity {
+        Validity::Expired => parsed_successful
+            .verification_error(&DccError::new(ErrorCode::Expired, "Certificate is expired"))
+            .build(),
+        Validity::NotYetValid => parsed_successful
+            .verification_error(&DccError::new(
+                ErrorCode::NotYetValid,
+                "Certificate is not yet valid",
+            ))
+            .build(),
+        _ => parsed_successful.build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cose::CoseSingleSigned;
+    use crate::issue::write_dcc;
+    use crate::rules::RuleStore;
+    use crate::schema::SchemaViolation;
+    use crate::status::Status;
+    use crate::test_data::{FixtureKind, TestDataGenerator};
+    use crate::trust_store::TrustStore;
+
+    const TEST_DCC: &str = "HC1:6BFOXN*TS0BI$ZD-PHQ7I9AD66V5B22CH9M9ESI9XBHXK-%69LQOGI.*V76GCV4*XUA2P-FHT-HNTI4L6N$Q%UG/YL WO*Z7ON15 BM0VM.JQ$F4W17PG4.VAS5EG4V*BRL0K-RDY5RWOOH6PO9:TUQJAJG9-*NIRICVELZUZM9EN9-O9:PICIG805CZKHKB-43.E3KD3OAJ6*K6ZCY73JC3KD3ZQTWD3E.KLC8M3LP-89B9K+KB2KK3M*EDZI9$JAQJKKIJX2MM+GWHKSKE MCAOI8%MCU5VTQDPIMQK9*O7%NC.UTWA6QK.-T3-SY$NCU5CIQ 52744E09TBOC.UKMI$8R+1A7CPFRMLNKNM8JI0JPGN:0K7OOBRLY667SYHJL9B7VPO:SWLH1/S4KQQK0$5REQT5RN1FR%SHPLRKWJO8LQ84EBC$-P4A0V1BBR5XWB3OCGEK:$8HHOLQOZUJ*30Q8CD1";
+
+    const NOW: i64 = 1700000000;
+
+    fn verifier_for(kind: FixtureKind) -> (DccVerifier, String) {
+        let hc = DccVerifier::new()
+            .verify(TEST_DCC)
+            .health_certificate
+            .unwrap();
+        let fixture = TestDataGenerator::new("DE", NOW as f64)
+            .unwrap()
+            .fixture(kind, &hc)
+            .unwrap();
+
+        let mut trust_store = TrustStore::new();
+        trust_store.add_certificate(&fixture.kid, &fixture.certificate);
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+        options.set_now(NOW as f64);
+
+        (DccVerifier::with_options(options), fixture.dcc)
+    }
+
+    #[test]
+    fn it_decodes_without_trusted_issuer() {
+        let decoded = DccVerifier::new().verify(TEST_DCC);
+
+        assert!(decoded.successful);
+        assert_eq!(decoded.kid, "DEsVUSvpFAE=");
+        assert_eq!(decoded.status, Status::UnknownIssuer);
+        assert!(decoded.health_certificate.is_some());
+        assert!(decoded.data.is_object());
+    }
+
+    #[test]
+    fn it_verifies_a_valid_dcc() {
+        let (verifier, dcc) = verifier_for(FixtureKind::Valid);
+        let decoded = verifier.verify(&dcc);
+
+        assert!(decoded.signature_valid);
+        assert_eq!(decoded.status, Status::Valid);
+        assert_eq!(decoded.claims.issuer, Some(String::from("DE")));
+    }
+
+    #[test]
+    fn it_verifies_an_expired_dcc() {
+        let (verifier, dcc) = verifier_for(FixtureKind::Expired);
+        let decoded = verifier.verify(&dcc);
+
+        assert!(decoded.signature_valid);
+        assert_eq!(decoded.status, Status::Expired);
+        assert_eq!(decoded.error_code, ErrorCode::Expired);
+    }
+
+    #[test]
+    fn it_verifies_at_the_end_of_time() {
+        let mut options = ParseOptions::new();
+        options.set_now(f64::MAX);
+        options.set_clock_skew(u32::MAX);
+        let decoded = DccVerifier::with_options(options).verify(TEST_DCC);
+
+        assert!(decoded.successful);
+        assert_eq!(decoded.validity, Validity::Expired);
+    }
+
+    #[test]
+    fn it_decodes_detached_content_which_is_not_cbor() {
+        let headers = Value::Map(vec![(Value::from(1), Value::from(-7))]);
+        let mut protected = Vec::new();
+        ciborium::ser::into_writer(&headers, &mut protected).unwrap();
+        let cose = CoseSingleSigned::try_from([
+            Value::from(protected),
+            Value::Map(vec![]),
+            Value::Null,
+            Value::Bytes(vec![]),
+        ])
+        .unwrap();
+        let mut options = ParseOptions::new();
+        options.set_detached_payload(b"not cbor \xff");
+        let decoded = DccVerifier::with_options(options).verify(&write_dcc(&cose).unwrap());
+
+        assert!(decoded.successful);
+        assert!(decoded
+            .warnings
+            .contains(&String::from("The payload is not cbor encoded")));
+        assert_eq!(decoded.status, Status::UnknownIssuer);
+    }
+
+    #[test]
+    fn rules_which_are_not_evaluated_fail() {
+        let mut hc = DccVerifier::new()
+            .verify(TEST_DCC)
+            .health_certificate
+            .unwrap();
+        hc.r = None;
+        let fixture = TestDataGenerator::new("DE", NOW as f64)
+            .unwrap()
+            .fixture(FixtureKind::Valid, &hc)
+            .unwrap();
+
+        let mut trust_store = TrustStore::new();
+        trust_store.add_certificate(&fixture.kid, &fixture.certificate);
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+        options.set_now(NOW as f64);
+        assert_eq!(
+            DccVerifier::with_options(options.clone())
+                .verify(&fixture.dcc)
+                .status,
+            Status::Valid
+        );
+
+        // The rules need a vaccination, test or recovery group
+        options.set_rules(&RuleStore::new(), "NL");
+        let decoded = DccVerifier::with_options(options).verify(&fixture.dcc);
+
+        assert!(decoded.signature_valid);
+        assert!(decoded.rule_results.is_empty());
+        assert_eq!(decoded.status, Status::RulesFailed);
+    }
+
+    #[test]
+    fn it_verifies_a_batch() {
+        let (verifier, dcc) = verifier_for(FixtureKind::Valid);
+        let batch = verifier.verify_batch(&[dcc.as_str(), TEST_DCC, "HC1:INVALID", &dcc]);
+
+        assert_eq!(batch.results.len(), 4);
+        assert_eq!(batch.results[1].status, Status::UnknownIssuer);
+        assert_eq!(batch.statistics.total, 4);
+        assert_eq!(batch.statistics.statuses[&Status::Valid], 2);
+        assert_eq!(batch.statistics.statuses[&Status::NotParseable], 1);
+        assert_eq!(batch.statistics.issuer_countries["DE"], 3);
+        assert_eq!(batch.statistics.kids["DEsVUSvpFAE="], 1);
+    }
+
+    #[test]
+    fn it_serializes_to_json() {
+        let decoded = DccVerifier::new().verify("HC1:INVALID");
+        let json = serde_json::to_value(&decoded).unwrap();
+
+        assert_eq!(json["status"], "NOT_PARSEABLE");
+        assert_eq!(json["decode_error"]["code"], "base45");
+    }
+
+    #[test]
+    fn schema_violations_have_path_and_message() {
+        let violation = SchemaViolation {
+            path: String::from("r[0].fr"),
+            message: String::from("is not a date (YYYY-MM-DD)"),
+        };
+        let decoded = DecodedDccBuilder::new()
+            .schema_violations(&[violation])
+            .build();
+        let json = serde_json::to_value(&decoded).unwrap();
+
+        assert_eq!(json["schema_violations"][0]["path"], "r[0].fr");
+        assert_eq!(
+            json["schema_violations"][0]["message"],
+            "is not a date (YYYY-MM-DD)"
+        );
+    }
+}
@@ -0,0 +1,1113 @@
+use crate::claims::CwtClaims;
+use crate::cose::CoseSingleSigned;
+use crate::error::{DccError, ErrorCode, WithCode};
+use crate::parse_options::ParseOptions;
+use crate::rules::CertificateType;
+use crate::trust_store::TrustStore;
+use ciborium::value::Value;
+use ecdsa::signature::hazmat::PrehashVerifier;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::traits::PublicKeyParts;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use x509_parser::oid_registry::*;
+use x509_parser::prelude::*;
+
+/// Extended key usage of a DSC allowed to sign test certificates
+pub(crate) const EKU_TEST: &str = "1.3.6.1.4.1.1847.2021.1.1";
+/// Extended key usage of a DSC allowed to sign vaccination certificates
+pub(crate) const EKU_VACCINATION: &str = "1.3.6.1.4.1.1847.2021.1.2";
+/// Extended key usage of a DSC allowed to sign recovery certificates
+pub(crate) const EKU_RECOVERY: &str = "1.3.6.1.4.1.1847.2021.1.3";
+
+///
+/// Verify the signature of a COSE message
+///
+/// When the message contains an x5chain header, the embedded certificate
+/// is used to verify the signature. The embedded certificate must be a
+/// known certificate or be issued by a CSCA in the trust store. Otherwise
+/// the certificate is looked up by kid in the trust store.
+///
+/// Deviations from the specifications which are tolerated are added
+/// to the warnings. The public keys of certificates that are already
+/// loaded are taken from the cache.
+///
+pub(crate) fn verify_signature_with_keys(
+    cose: &CoseSingleSigned,
+    options: &ParseOptions,
+    keys: &mut KeyCache,
+    warnings: &mut Vec<String>,
+) -> Result<(), DccError> {
+    let trust_store = options.trust_store();
+    let x5chain = cose.x5chain();
+
+    if x5chain.is_empty() {
+        let kid = cose.kid().unwrap_or_default();
+
+        let issuer_cert = match trust_store.find_certificate(&kid) {
+            Some(c) => c,
+            None => {
+                return Err(format!(
+                    "No public certificate known for issuer with kid {}",
+                    kid
+                ))
+                .code(ErrorCode::UnknownKid)
+            }
+        };
+
+        // Base64 decode issuer certificate
+        let issuer_cert = match base64::decode(issuer_cert) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Error on base64 decoding issuer cert: {}", e))
+                    .code(ErrorCode::BadCertificate)
+            }
+        };
+
+        return verify_with_certificate(cose, &issuer_cert, options, keys, warnings);
+    }
+
+    verify_with_certificate(cose, &x5chain[0], options, keys, warnings)?;
+    verify_trust(&x5chain, trust_store, options.now()).code(ErrorCode::UntrustedCertificate)
+}
+
+///
+/// Verify the signature of a COSE message with a DER encoded certificate
+///
+fn verify_with_certificate(
+    cose: &CoseSingleSigned,
+    certificate: &[u8],
+    options: &ParseOptions,
+    keys: &mut KeyCache,
+    warnings: &mut Vec<String>,
+) -> Result<(), DccError> {
+    // Get public key from issuer certificate
+    let public_key = keys.public_key(certificate)?;
+
+    // The algorithm must match the curve of the public key
+    let algorithm = match cose.alg() {
+        Some(alg) => match Algorithm::from_cose(alg) {
+            Some(algorithm) => algorithm,
+            None => {
+                return Err(format!("Unsupported algorithm {}", alg))
+                    .code(ErrorCode::UnsupportedAlgorithm)
+            }
+        },
+        None => {
+            return Err(DccError::new(
+                ErrorCode::UnsupportedAlgorithm,
+                "No algorithm found in COSE headers",
+            ))
+        }
+    };
+
+    if public_key.algorithm() != algorithm {
+        return Err(format!(
+            "Algorithm {} doesn't match the {} public key of the issuer cert",
+            algorithm.name(),
+            public_key.curve()
+        ))
+        .code(ErrorCode::UnsupportedAlgorithm);
+    }
+
+    // The data to sign
+    let to_sign: [Value; 4] = cose.to_be_signed(options.external_aad());
+
+    // Cbor encode to_sign
+    let mut cbor_encoded = Vec::new();
+    if let Err(e) = ciborium::ser::into_writer(&to_sign, &mut cbor_encoded) {
+        return Err(format!("Error on cbor encoding to sign object: {}", e)).code(ErrorCode::Cbor);
+    }
+
+    // Convert cose signature to a raw r||s signature
+    let signature = decode_signature(cose.signature(), public_key, options.lenient(), warnings)
+        .code(ErrorCode::BadSignature)?;
+
+    // Verify with public key if the given signature is valid
+    match public_key.verify(&algorithm.digest(&cbor_encoded), &signature) {
+        Ok(..) => Ok(()),
+        Err(e) => Err(format!("Error verifying signature: {}", e)).code(ErrorCode::BadSignature),
+    }
+}
+
+///
+/// Public keys of DER encoded certificates
+///
+/// Loading a key parses the certificate, the cache loads each
+/// certificate once when many DCCs are verified.
+///
+#[derive(Default)]
+pub(crate) struct KeyCache {
+    keys: HashMap<Vec<u8>, Result<PublicKey, DccError>>,
+}
+
+impl KeyCache {
+    pub fn new() -> KeyCache {
+        KeyCache::default()
+    }
+
+    /// The public key of a certificate, loaded when not in the cache
+    fn public_key(&mut self, certificate: &[u8]) -> Result<&PublicKey, DccError> {
+        if !self.keys.contains_key(certificate) {
+            let public_key = load_public_key(certificate);
+            self.keys.insert(certificate.to_vec(), public_key);
+        }
+
+        match &self.keys[certificate] {
+            Ok(public_key) => Ok(public_key),
+            Err(e) => Err(e.clone()),
+        }
+    }
+}
+
+///
+/// Load the public key of a DER encoded certificate
+///
+fn load_public_key(certificate: &[u8]) -> Result<PublicKey, DccError> {
+    // Parse issuer certificate
+    let x509cert = match X509Certificate::from_der(certificate) {
+        Ok(c) => c.1,
+        Err(e) => {
+            return Err(format!("Couldn't load issuer cert: {}", e)).code(ErrorCode::BadCertificate)
+        }
+    };
+
+    PublicKey::from_certificate(&x509cert).code(ErrorCode::BadKey)
+}
+
+///
+/// Decode a COSE ECDSA signature
+///
+/// COSE requires the raw r||s form of the signature. Some issuers
+/// use a DER encoded signature instead, which is only accepted in
+/// lenient mode.
+///
+fn decode_signature(
+    signature: &[u8],
+    public_key: &PublicKey,
+    lenient: bool,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<u8>, String> {
+    let raw_signature_length = public_key.signature_length();
+
+    if signature.len() == raw_signature_length {
+        return Ok(signature.to_vec());
+    }
+
+    if !is_der_signature(signature) {
+        return Err(format!(
+            "Invalid signature of {} bytes, expected a raw r||s signature of {} bytes",
+            signature.len(),
+            raw_signature_length
+        ));
+    }
+
+    if !lenient {
+        return Err(format!(
+            "Signature is DER encoded ({} bytes), expected a raw r||s signature of {} bytes",
+            signature.len(),
+            raw_signature_length
+        ));
+    }
+
+    match public_key.der_to_raw(signature) {
+        Ok(s) => {
+            warnings.push(String::from(
+                "Signature is DER encoded instead of a raw r||s signature",
+            ));
+            Ok(s)
+        }
+        Err(e) => Err(format!("Error on parsing DER encoded signature: {}", e)),
+    }
+}
+
+///
+/// Check if a signature looks like a DER encoded sequence
+///
+fn is_der_signature(signature: &[u8]) -> bool {
+    match signature {
+        [0x30, 0x81, length, ..] => *length as usize == signature.len() - 3,
+        [0x30, length, ..] => *length as usize == signature.len() - 2,
+        _ => false,
+    }
+}
+
+///
+/// Check if the certificate chain from an x5chain header is trusted
+///
+/// Every certificate in the chain must be valid at the moment of
+/// verification, and the signing certificate must be a document signer,
+/// not a certificate authority. The signing certificate is trusted when
+/// it is known in the trust store by its kid. Otherwise every certificate
+/// in the chain must be issued by the next one, and the last one must be
+/// issued by a trusted CSCA or be the CSCA itself. Issuers must be a
+/// certificate authority.
+///
+fn verify_trust(chain: &[Vec<u8>], trust_store: &TrustStore, now: i64) -> Result<(), String> {
+    for certificate in chain {
+        verify_valid_at(certificate, now)?;
+    }
+
+    let signer = &chain[0];
+    verify_document_signer(signer)?;
+
+    if let Some(known) = trust_store.find_certificate(&certificate_kid(signer)) {
+        if base64::decode(known).as_ref() == Ok(signer) {
+            return Ok(());
+        }
+    }
+
+    for pair in chain.windows(2) {
+        verify_issued_by(&pair[0], &pair[1])
+            .map_err(|e| format!("Invalid certificate chain: {}", e))?;
+    }
+
+    let last = &chain[chain.len() - 1];
+
+    for csca in trust_store.cscas() {
+        let csca = match base64::decode(csca) {
+            Ok(c) => c,
+            Err(_e) => continue,
+        };
+
+        let is_issuer = chain.len() > 1 && &csca == last;
+        if is_issuer || verify_issued_by(last, &csca).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(String::from(
+        "The embedded certificate is not issued by a trusted CSCA",
+    ))
+}
+
+///
+/// Verify a DER encoded certificate is a document signer, which is no certificate authority
+///
+fn verify_document_signer(certificate: &[u8]) -> Result<(), String> {
+    let certificate = match X509Certificate::from_der(certificate) {
+        Ok(c) => c.1,
+        Err(e) => return Err(format!("Couldn't load certificate: {}", e)),
+    };
+
+    if certificate.tbs_certificate.is_ca() {
+        return Err(format!(
+            "Signing certificate {} is a certificate authority, not a document signer",
+            certificate.subject()
+        ));
+    }
+
+    Ok(())
+}
+
+///
+/// Verify a DER encoded certificate is valid at the moment, a unix timestamp
+///
+fn verify_valid_at(certificate: &[u8], now: i64) -> Result<(), String> {
+    let certificate = match X509Certificate::from_der(certificate) {
+        Ok(c) => c.1,
+        Err(e) => return Err(format!("Couldn't load certificate: {}", e)),
+    };
+
+    if !certificate
+        .validity()
+        .is_valid_at(ASN1Time::from_timestamp(now))
+    {
+        return Err(format!(
+            "Certificate {} is expired or not yet valid",
+            certificate.subject()
+        ));
+    }
+
+    Ok(())
+}
+
+///
+/// Verify a DER encoded certificate is issued by another DER encoded certificate,
+/// which must be a certificate authority
+///
+fn verify_issued_by(certificate: &[u8], issuer: &[u8]) -> Result<(), String> {
+    let certificate = match X509Certificate::from_der(certificate) {
+        Ok(c) => c.1,
+        Err(e) => return Err(format!("Couldn't load certificate: {}", e)),
+    };
+
+    let issuer = match X509Certificate::from_der(issuer) {
+        Ok(c) => c.1,
+        Err(e) => return Err(format!("Couldn't load issuer certificate: {}", e)),
+    };
+
+    if certificate.issuer() != issuer.subject() {
+        return Err(String::from("Certificate issuer doesn't match"));
+    }
+
+    if !issuer.tbs_certificate.is_ca() {
+        return Err(format!(
+            "Issuer {} is not a certificate authority",
+            issuer.subject()
+        ));
+    }
+
+    let signature_algorithm = &certificate.signature_algorithm.algorithm;
+    let algorithm = match Algorithm::from_certificate_signature(signature_algorithm) {
+        Some(algorithm) => algorithm,
+        None => {
+            return Err(format!(
+                "Unsupported certificate signature algorithm {}",
+                signature_algorithm
+            ))
+        }
+    };
+
+    let public_key = PublicKey::from_certificate(&issuer)?;
+
+    let signature = match public_key.der_to_raw(certificate.signature_value.data) {
+        Ok(s) => s,
+        Err(e) => return Err(format!("Error on parsing certificate signature: {}", e)),
+    };
+
+    let digest = algorithm.digest(certificate.tbs_certificate.as_ref());
+    match public_key.verify(&digest, &signature) {
+        Ok(..) => Ok(()),
+        Err(e) => Err(format!("Error verifying certificate signature: {}", e)),
+    }
+}
+
+///
+/// ECDSA signature algorithms
+///
+/// https://datatracker.ietf.org/doc/html/rfc8152#section-8.1
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    ES256,
+    ES384,
+    ES512,
+    PS256,
+}
+
+impl Algorithm {
+    /// Algorithm from the COSE alg header
+    fn from_cose(alg: i128) -> Option<Algorithm> {
+        match alg {
+            -7 => Some(Algorithm::ES256),
+            -35 => Some(Algorithm::ES384),
+            -36 => Some(Algorithm::ES512),
+            -37 => Some(Algorithm::PS256),
+            _ => None,
+        }
+    }
+
+    /// Algorithm from the signature algorithm of a certificate
+    fn from_certificate_signature(oid: &Oid) -> Option<Algorithm> {
+        match oid {
+            oid if oid == &OID_SIG_ECDSA_WITH_SHA256 => Some(Algorithm::ES256),
+            oid if oid == &OID_SIG_ECDSA_WITH_SHA384 => Some(Algorithm::ES384),
+            oid if oid == &OID_SIG_ECDSA_WITH_SHA512 => Some(Algorithm::ES512),
+            _ => None,
+        }
+    }
+
+    /// Name of the algorithm
+    fn name(&self) -> &str {
+        match *self {
+            Algorithm::ES256 => "ES256",
+            Algorithm::ES384 => "ES384",
+            Algorithm::ES512 => "ES512",
+            Algorithm::PS256 => "PS256",
+        }
+    }
+
+    /// Hash the data with the hash function of the algorithm
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match *self {
+            Algorithm::ES256 | Algorithm::PS256 => Sha256::digest(data).to_vec(),
+            Algorithm::ES384 => Sha384::digest(data).to_vec(),
+            Algorithm::ES512 => Sha512::digest(data).to_vec(),
+        }
+    }
+}
+
+///
+/// Elliptic curve or RSA public key of a certificate
+///
+enum PublicKey {
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+    P521(p521::ecdsa::VerifyingKey),
+    Rsa(rsa::pss::VerifyingKey<Sha256>),
+}
+
+impl PublicKey {
+    /// Get the public key from a certificate, the curve is
+    /// determined by the parameters of the public key
+    fn from_certificate(certificate: &X509Certificate) -> Result<PublicKey, String> {
+        let public_key = certificate.public_key();
+
+        if public_key.algorithm.algorithm == OID_PKCS1_RSAENCRYPTION {
+            return match rsa::RsaPublicKey::from_pkcs1_der(public_key.subject_public_key.data) {
+                Ok(key) => Ok(PublicKey::Rsa(rsa::pss::VerifyingKey::new(key))),
+                Err(e) => Err(format!("Couldn't load RSA public key: {}", e)),
+            };
+        }
+
+        if public_key.algorithm.algorithm != OID_KEY_TYPE_EC_PUBLIC_KEY {
+            return Err(format!(
+                "Unsupported public key algorithm {}",
+                public_key.algorithm.algorithm
+            ));
+        }
+
+        let curve = match &public_key.algorithm.parameters {
+            Some(parameters) => match parameters.as_oid() {
+                Ok(curve) => curve.clone(),
+                Err(_e) => return Err(String::from("Public key has no named curve")),
+            },
+            None => return Err(String::from("Public key has no named curve")),
+        };
+
+        let key = public_key.subject_public_key.data;
+        let result = match curve {
+            curve if curve == OID_EC_P256 => {
+                p256::ecdsa::VerifyingKey::from_sec1_bytes(key).map(PublicKey::P256)
+            }
+            curve if curve == OID_NIST_EC_P384 => {
+                p384::ecdsa::VerifyingKey::from_sec1_bytes(key).map(PublicKey::P384)
+            }
+            curve if curve == OID_NIST_EC_P521 => {
+                p521::ecdsa::VerifyingKey::from_sec1_bytes(key).map(PublicKey::P521)
+            }
+            curve => return Err(format!("Unsupported curve {}", curve)),
+        };
+
+        result.map_err(|e| format!("Couldn't load public key: {}", e))
+    }
+
+    /// Name of the curve
+    fn curve(&self) -> &str {
+        match self {
+            PublicKey::P256(..) => "P-256",
+            PublicKey::P384(..) => "P-384",
+            PublicKey::P521(..) => "P-521",
+            PublicKey::Rsa(..) => "RSA",
+        }
+    }
+
+    /// The COSE algorithm to use with this curve
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            PublicKey::P256(..) => Algorithm::ES256,
+            PublicKey::P384(..) => Algorithm::ES384,
+            PublicKey::P521(..) => Algorithm::ES512,
+            PublicKey::Rsa(..) => Algorithm::PS256,
+        }
+    }
+
+    /// Length of a raw r||s signature, or the modulus of an RSA key
+    fn signature_length(&self) -> usize {
+        match self {
+            PublicKey::P256(..) => 64,
+            PublicKey::P384(..) => 96,
+            PublicKey::P521(..) => 132,
+            PublicKey::Rsa(key) => key.as_ref().size(),
+        }
+    }
+
+    /// Convert a DER encoded signature into a raw r||s signature
+    fn der_to_raw(&self, signature: &[u8]) -> Result<Vec<u8>, ecdsa::Error> {
+        Ok(match self {
+            PublicKey::P256(..) => p256::ecdsa::Signature::from_der(signature)?
+                .to_bytes()
+                .to_vec(),
+            PublicKey::P384(..) => p384::ecdsa::Signature::from_der(signature)?
+                .to_bytes()
+                .to_vec(),
+            PublicKey::P521(..) => p521::ecdsa::Signature::from_der(signature)?
+                .to_bytes()
+                .to_vec(),
+            PublicKey::Rsa(..) => return Err(ecdsa::Error::new()),
+        })
+    }
+
+    /// Verify a raw r||s or RSASSA-PSS signature of a hashed message
+    fn verify(&self, digest: &[u8], signature: &[u8]) -> Result<(), ecdsa::Error> {
+        match self {
+            PublicKey::P256(key) => {
+                key.verify_prehash(digest, &p256::ecdsa::Signature::from_slice(signature)?)
+            }
+            PublicKey::P384(key) => {
+                key.verify_prehash(digest, &p384::ecdsa::Signature::from_slice(signature)?)
+            }
+            PublicKey::P521(key) => {
+                key.verify_prehash(digest, &p521::ecdsa::Signature::from_slice(signature)?)
+            }
+            PublicKey::Rsa(key) => {
+                key.verify_prehash(digest, &rsa::pss::Signature::try_from(signature)?)
+            }
+        }
+    }
+}
+
+///
+/// The DER encoded certificate which signed a COSE message, embedded
+/// in the x5chain header or found in the trust store by kid
+///
+pub(crate) fn signing_certificate(
+    cose: &CoseSingleSigned,
+    trust_store: &TrustStore,
+) -> Option<Vec<u8>> {
+    if let Some(certificate) = cose.x5chain().into_iter().next() {
+        return Some(certificate);
+    }
+
+    let certificate = trust_store.find_certificate(&cose.kid()?)?;
+    base64::decode(certificate).ok()
+}
+
+///
+/// Verify the DSC may sign the health certificate of the claims
+///
+/// The country of the DSC must match the issuer claim. When the DSC has
+/// extended key usages for types of health certificates, it may only sign
+/// those types. Without them, it may sign all types.
+///
+pub(crate) fn verify_certificate_usage(
+    certificate: &[u8],
+    claims: &CwtClaims,
+) -> Result<(), DccError> {
+    let certificate = match X509Certificate::from_der(certificate) {
+        Ok(c) => c.1,
+        Err(e) => {
+            return Err(format!("Couldn't load issuer cert: {}", e)).code(ErrorCode::BadCertificate)
+        }
+    };
+
+    let country = certificate
+        .subject()
+        .iter_country()
+        .next()
+        .and_then(|country| country.as_str().ok());
+    if let (Some(country), Some(issuer)) = (country, &claims.issuer) {
+        if !country.eq_ignore_ascii_case(issuer) {
+            return Err(format!(
+                "The DSC of country {} may not sign for issuer {}",
+                country, issuer
+            ))
+            .code(ErrorCode::WrongCountry);
+        }
+    }
+
+    let ekus: Vec<String> = match certificate.tbs_certificate.extended_key_usage() {
+        Some((_, eku)) => eku.other.iter().map(|oid| oid.to_id_string()).collect(),
+        None => Vec::new(),
+    };
+    let dcc_ekus: Vec<&str> = [EKU_TEST, EKU_VACCINATION, EKU_RECOVERY]
+        .iter()
+        .copied()
+        .filter(|eku| ekus.iter().any(|other| other == eku))
+        .collect();
+    let required = match claims.certificate_type() {
+        Some(CertificateType::Test) => Some(EKU_TEST),
+        Some(CertificateType::Vaccination) => Some(EKU_VACCINATION),
+        Some(CertificateType::Recovery) => Some(EKU_RECOVERY),
+        _ => None,
+    };
+
+    match required {
+        Some(required) if !dcc_ekus.is_empty() && !dcc_ekus.contains(&required) => Err(format!(
+            "The DSC may not sign this type of certificate, it's restricted to {}",
+            dcc_ekus.join(", ")
+        ))
+        .code(ErrorCode::WrongCertificateType),
+        _ => Ok(()),
+    }
+}
+
+///
+/// The kid of a DER encoded certificate, which are the first 8 bytes
+/// of the SHA-256 fingerprint, base64 encoded
+///
+pub fn certificate_kid(certificate: &[u8]) -> String {
+    base64::encode(&Sha256::digest(certificate)[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecdsa::signature::Signer;
+    use std::convert::TryFrom;
+
+    fn verify_signature(
+        cose: &CoseSingleSigned,
+        options: &ParseOptions,
+        warnings: &mut Vec<String>,
+    ) -> Result<(), DccError> {
+        verify_signature_with_keys(cose, options, &mut KeyCache::new(), warnings)
+    }
+
+    const CSCA: &str = "MIIBmDCCAT2gAwIBAgIUKVdEbBu9CY7AerKKfxgPiWHCaFYwCgYIKoZIzj0EAwIwITELMAkGA1UEBhMCWFgxEjAQBgNVBAMMCVRlc3QgQ1NDQTAeFw0yNjEwMTgxOTI0MTRaFw00NjEwMTMxOTI0MTRaMCExCzAJBgNVBAYTAlhYMRIwEAYDVQQDDAlUZXN0IENTQ0EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASkFPGtPyGCpYJu2Gd5nyDpdB2hYGdtHeZln4Am+sW4xY1X4u1nyKgH0AMj1YpchHLpubBJtdb4DvjsBYflpeJHo1MwUTAdBgNVHQ4EFgQUatBfboWIY1s49/62ckrY0BvdvOowHwYDVR0jBBgwFoAUatBfboWIY1s49/62ckrY0BvdvOowDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEAnfP4SQ9Z8eqCDdo38a6QuOEm9t3qD9IbuZL1eYFc6L0CIQCnnJyV2VT2JwmLNSrEse/iu4AY+YuVQiqyTRo/bqo9EA==";
+
+    const OTHER_CSCA: &str = "MIIBmTCCAT+gAwIBAgIUK+JoJnSekbiNLX72ZeFq20Fary4wCgYIKoZIzj0EAwIwIjELMAkGA1UEBhMCWFgxEzARBgNVBAMMCk90aGVyIENTQ0EwHhcNMjYxMDE4MTkyNDE0WhcNNDYxMDEzMTkyNDE0WjAiMQswCQYDVQQGEwJYWDETMBEGA1UEAwwKT3RoZXIgQ1NDQTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABG1LK8ZIWTxo4UhmA/nycmIVdhfMp4AUNgtgva6EeQKAN/0dTWdbFUZAybYPZ9IPDWyFUSKpA08DRy09FSAmAPejUzBRMB0GA1UdDgQWBBTNhXv1HuC0oXoiuQ0+C5Q8Ph0wjTAfBgNVHSMEGDAWgBTNhXv1HuC0oXoiuQ0+C5Q8Ph0wjTAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQCGk95iTP8oaKbGJxzJ9X6G8mhbWDPOPFN1YBHG7Y4baQIgEHzc4GlPyAumamcdTC/TqExh7HYb4cxOyZxRDnmO4Mk=";
+
+    /// Document signer certificate, issued by CSCA
+    const DSC: &str = "MIIByTCCAW+gAwIBAgIUQabYGd8Kh0fyjur+aGCRIeEDgswwCgYIKoZIzj0EAwIwITELMAkGA1UEBhMCWFgxEjAQBgNVBAMMCVRlc3QgQ1NDQTAeFw0yNjEwMTgxOTI0MTRaFw0zNjEwMTUxOTI0MTRaMCAxCzAJBgNVBAYTAlhYMREwDwYDVQQDDAhUZXN0IERTQzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABIMFeMp7qZj3CLRoPYumuI6U1NL/GaEFeOKfJl2PhJGPLh8GjpU/A5yIQuU3NdxKzJW+GtE6yn7uaoow5LEBtp+jgYUwgYIwMAYDVR0lBCkwJwYLKwYBBAGON49lAQEGCysGAQQBjjePZQECBgsrBgEEAY43j2UBAzAOBgNVHQ8BAf8EBAMCB4AwHQYDVR0OBBYEFHvbNilQ/UEu9iAu+Vn+a+PQOrO+MB8GA1UdIwQYMBaAFGrQX26FiGNbOPf+tnJK2NAb3bzqMAoGCCqGSM49BAMCA0gAMEUCIFkZgeUZanx4owS4SToL3JzKr0WgN83XQTQooWw/X22ZAiEA6S1gt6Hg8u75hd+dOvPRpfhL5iWTJ+Wq8M4RWE+E+/4=";
+
+    /// Private key of DSC
+    const DSC_KEY: [u8; 32] = [
+        0x07, 0x2a, 0x23, 0xc1, 0x78, 0x39, 0x5b, 0x59, 0x23, 0xde, 0xa5, 0x2b, 0xac, 0xc6, 0x55,
+        0x08, 0x0a, 0x92, 0x25, 0x0e, 0x1f, 0x30, 0x5b, 0x2b, 0xb1, 0x5b, 0x67, 0x87, 0x56, 0x18,
+        0x6a, 0x57,
+    ];
+
+    /// Self signed P-384 document signer certificate
+    const DSC_P384: &str = "MIIB5zCCAWygAwIBAgIUeVoQOe7bLbrtp/mdOde1KK8pTzEwCgYIKoZIzj0EAwIwKjELMAkGA1UEBhMCWFgxGzAZBgNVBAMMElRlc3QgRFNDIHNlY3AzODRyMTAeFw0yNjEwMTgxOTI5NDNaFw00NjEwMTMxOTI5NDNaMCoxCzAJBgNVBAYTAlhYMRswGQYDVQQDDBJUZXN0IERTQyBzZWNwMzg0cjEwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAASMgZfLPnd5JGue/+zQ/Un5Kr0apgNs6S2q4Mdva3qBaPNEhj1Y60bgjyB8qcsMrpTDdr716uzivf6ZDVEWL0C9WfUtguG1U2o44DyRJlgxfc9tIOA8nlz0vFikAk9LeXyjUzBRMB0GA1UdDgQWBBTnYHL/0V6IgNpn1pBH3CusoOwS3zAfBgNVHSMEGDAWgBTnYHL/0V6IgNpn1pBH3CusoOwS3zAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA2kAMGYCMQDzHRs4XiStG9BcmBzHU4SxnSGHZqGr4B2bI21z96veVQah2ZJ9IBpbz9DxzIMkI8gCMQDMLt75k/ZyCny5dBebelz70XzlDvkHsqa47ljh6ca/c2IBUfgHq7/kq7hhb/dx/Bk=";
+
+    /// Private key of DSC_P384
+    const DSC_P384_KEY: [u8; 48] = [
+        0x45, 0x1a, 0x4b, 0x2a, 0x6a, 0x67, 0x49, 0x8f, 0xde, 0x8d, 0x40, 0xb0, 0xa6, 0x99, 0x90,
+        0x86, 0xdf, 0x43, 0xf4, 0xe2, 0x5e, 0x4e, 0x89, 0x62, 0x23, 0x97, 0xc9, 0x38, 0xac, 0x1a,
+        0x65, 0xe3, 0xaa, 0x1b, 0x30, 0x17, 0x2e, 0x1e, 0x76, 0x40, 0x8d, 0x3d, 0xe0, 0x73, 0xfe,
+        0x8c, 0x6a, 0x2e,
+    ];
+
+    /// Self signed P-521 document signer certificate
+    const DSC_P521: &str = "MIICMTCCAZKgAwIBAgIUU9fvN1zdf2SBjQai4Ss37P9vx4YwCgYIKoZIzj0EAwIwKjELMAkGA1UEBhMCWFgxGzAZBgNVBAMMElRlc3QgRFNDIHNlY3A1MjFyMTAeFw0yNjEwMTgxOTI5NDNaFw00NjEwMTMxOTI5NDNaMCoxCzAJBgNVBAYTAlhYMRswGQYDVQQDDBJUZXN0IERTQyBzZWNwNTIxcjEwgZswEAYHKoZIzj0CAQYFK4EEACMDgYYABACM3G02EResozKRa62mTHcR4TrPxH2Mv+7HMOlCpuFfZhbKIvnZkBC866g8mll+Dwdo9Fdf+klnHrTlKsSc3okGvwEvdMAUcFozRU5Nury916yrR3NwVA+JOsJlOL3oiEi4kU/lfgJvuTLuvOmxCDE3S+XnMckawMiPbuWCUhhmnbYoxqNTMFEwHQYDVR0OBBYEFL/jEf7pARug87pGQHlMnH4dXrz+MB8GA1UdIwQYMBaAFL/jEf7pARug87pGQHlMnH4dXrz+MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDgYwAMIGIAkIAyk714DPFrC9D8N/+MuTw9olYj4k1doZZqujHjKKXH+uXcllZ5ptQysHA2jv6EUyIiZFFBVkA55XOvE8/teEJHHkCQgGVkfAbhVrCpqsqq3HfOU5MveOBdPyKySwA1U3FeqXG+WrOzmNu79izXQV/fIvhOeF4yrkFGZdjOah0jhyc1pABRA==";
+
+    /// Private key of DSC_P521
+    const DSC_P521_KEY: [u8; 66] = [
+        0x01, 0x01, 0xaf, 0x0e, 0x80, 0xee, 0x04, 0xa0, 0x3d, 0x78, 0x71, 0x49, 0x94, 0x45, 0x44,
+        0x68, 0xdd, 0x51, 0x6b, 0xc4, 0xc8, 0x79, 0xc3, 0x05, 0xf2, 0xf7, 0xa2, 0x4c, 0x24, 0xa0,
+        0x01, 0xcd, 0x1f, 0xc7, 0xe2, 0x5b, 0x80, 0x6a, 0x7d, 0x47, 0x52, 0x64, 0x7c, 0x0f, 0xda,
+        0x37, 0x43, 0x3b, 0x5b, 0x0a, 0x70, 0xac, 0x48, 0xf8, 0x76, 0xd0, 0x62, 0xf0, 0x93, 0x59,
+        0xed, 0x32, 0x7c, 0x07, 0x9f, 0x89,
+    ];
+
+    /// Sign with the private key of DSC
+    fn sign_p256(data: &[u8]) -> Vec<u8> {
+        let key = p256::ecdsa::SigningKey::from_slice(&DSC_KEY).unwrap();
+        let signature: p256::ecdsa::Signature = key.sign(data);
+        signature.to_vec()
+    }
+
+    /// Sign with the private key of DSC_P384
+    fn sign_p384(data: &[u8]) -> Vec<u8> {
+        let key = p384::ecdsa::SigningKey::from_slice(&DSC_P384_KEY).unwrap();
+        let signature: p384::ecdsa::Signature = key.sign(data);
+        signature.to_vec()
+    }
+
+    /// Sign with the private key of DSC_P521
+    fn sign_p521(data: &[u8]) -> Vec<u8> {
+        let key = p521::ecdsa::SigningKey::from_slice(&DSC_P521_KEY).unwrap();
+        let signature: p521::ecdsa::Signature = key.sign(data);
+        signature.to_vec()
+    }
+
+    /// Helper function to encode an value to cbor bytes
+    fn cbor_encode(value: &Value) -> Vec<u8> {
+        let mut encoded: Vec<u8> = Vec::new();
+        ciborium::ser::into_writer(value, &mut encoded).unwrap();
+
+        encoded
+    }
+
+    /// A COSE message signed by DSC, the unprotected headers are given
+    fn signed_cose(unprotected_headers: Vec<(Value, Value)>) -> CoseSingleSigned {
+        signed_cose_with_aad(unprotected_headers, &[])
+    }
+
+    /// A COSE message signed by DSC with external additional authenticated data
+    fn signed_cose_with_aad(
+        unprotected_headers: Vec<(Value, Value)>,
+        external_aad: &[u8],
+    ) -> CoseSingleSigned {
+        signed_cose_with(-7, sign_p256, unprotected_headers, external_aad)
+    }
+
+    /// A COSE message with the given algorithm and signing function
+    fn signed_cose_with(
+        alg: i128,
+        sign: fn(&[u8]) -> Vec<u8>,
+        unprotected_headers: Vec<(Value, Value)>,
+        external_aad: &[u8],
+    ) -> CoseSingleSigned {
+        let protected = cbor_encode(&Value::Map(vec![(Value::from(1), Value::from(alg))]));
+        let payload = cbor_encode(&Value::from("some_payload"));
+
+        let mut values = [
+            Value::from(protected),
+            Value::Map(unprotected_headers),
+            Value::from(payload),
+            Value::Bytes(vec![]),
+        ];
+
+        let unsigned = CoseSingleSigned::try_from(values.clone()).unwrap();
+        let to_sign = cbor_encode(&Value::Array(unsigned.to_be_signed(external_aad).to_vec()));
+        values[3] = Value::from(sign(&to_sign));
+
+        CoseSingleSigned::try_from(values).unwrap()
+    }
+
+    /// Options trusting the certificates in the store
+    fn options(trust_store: TrustStore) -> ParseOptions {
+        let mut options = ParseOptions::new();
+        options.set_trust_store(&trust_store);
+
+        options
+    }
+
+    /// Certificate issued by DSC, which is not a certificate authority
+    const LEAF: &str = "MIIBQDCB56ADAgECAhQlBiJ2cnNsWZYa1FVEVm2ahslv9zAKBggqhkjOPQQDAjAgMQswCQYDVQQGEwJYWDERMA8GA1UEAwwIVGVzdCBEU0MwHhcNMjYxMDE4MTkyNDE0WhcNMzYxMDE0MTkyNDE0WjAhMQswCQYDVQQGEwJYWDESMBAGA1UEAwwJVGVzdCBMZWFmMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAExX+5tqbWSnlLubj0SCw7eRyKak6QEuLvyC//avNmru/cAhZadFmS+GtL6/ZXU7jye1djfaSql9cQRIp+IymTEDAKBggqhkjOPQQDAgNIADBFAiBFgvvzKF9FRsWBz06g45ZLcOznqDdOSbdytkV6TF8ynwIhAOWUeurIfQDHkzvTtVoQQ8/NojPw2X/Sa2UWd4bUJKZF";
+
+    /// Private key of LEAF
+    const LEAF_KEY: [u8; 32] = [
+        0x8b, 0x6a, 0x9c, 0x5b, 0x90, 0xb0, 0xd7, 0xf3, 0xa5, 0x60, 0x30, 0x95, 0x57, 0x74, 0x9d,
+        0x22, 0x95, 0xb0, 0x40, 0x2c, 0xea, 0x28, 0x03, 0x1d, 0x7d, 0xa8, 0x4b, 0x5c, 0x35, 0x78,
+        0xb4, 0xd4,
+    ];
+
+    /// Self signed CSCA of which the private key is known
+    const SIGNING_CSCA: &str = "MIIBXTCCAQOgAwIBAgIUXEFrzRi/uzwiciAV1gCtYspMzCkwCgYIKoZIzj0EAwIwJDELMAkGA1UEBhMCWFgxFTATBgNVBAMMDFNpZ25pbmcgQ1NDQTAeFw0yNjEwMTgxOTI0MTRaFw00NjEwMTMxOTI0MTRaMCQxCzAJBgNVBAYTAlhYMRUwEwYDVQQDDAxTaWduaW5nIENTQ0EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAStKSMQ0ZtGrSM93w5KVICSk3PtNTMXYKgd9IeUD1gCoMx8TclDmnwen8JbyNx6jHs0bvtSuIPzb1Ns+CR7CAEsoxMwETAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQDy036GPAGNpeAZxDRsD1fpkAly9EjR2gCRCbH+ZYdxmAIgCOSr1+ITk1qXy+Wr2e0Z5jnAeDolvgjOVhznComa2kU=";
+
+    /// Private key of SIGNING_CSCA
+    const SIGNING_CSCA_KEY: [u8; 32] = [
+        0x31, 0xa7, 0x05, 0x87, 0xe3, 0x89, 0x52, 0xb8, 0xd4, 0x89, 0x0c, 0x5b, 0x56, 0xfa, 0x26,
+        0xd7, 0x60, 0xe9, 0x5d, 0x87, 0xff, 0x9a, 0x55, 0x54, 0x2b, 0xb9, 0x59, 0xbe, 0xde, 0x87,
+        0x51, 0xd8,
+    ];
+
+    /// Sign with the private key of SIGNING_CSCA
+    fn sign_csca(data: &[u8]) -> Vec<u8> {
+        let key = p256::ecdsa::SigningKey::from_slice(&SIGNING_CSCA_KEY).unwrap();
+        let signature: p256::ecdsa::Signature = key.sign(data);
+        signature.to_vec()
+    }
+
+    /// Sign with the private key of LEAF
+    fn sign_leaf(data: &[u8]) -> Vec<u8> {
+        let key = p256::ecdsa::SigningKey::from_slice(&LEAF_KEY).unwrap();
+        let signature: p256::ecdsa::Signature = key.sign(data);
+        signature.to_vec()
+    }
+
+    fn x5chain_cose() -> CoseSingleSigned {
+        signed_cose(vec![(
+            Value::from(33),
+            Value::from(base64::decode(DSC).unwrap()),
+        )])
+    }
+
+    #[test]
+    fn it_verifies_with_kid() {
+        let cose = signed_cose(vec![(Value::from(4), Value::from(vec![1u8, 2]))]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn keys_are_loaded_once() {
+        let cose = signed_cose(vec![(Value::from(4), Value::from(vec![1u8, 2]))]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+        let options = options(store);
+
+        let mut keys = KeyCache::new();
+        for _ in 0..2 {
+            assert_eq!(
+                verify_signature_with_keys(&cose, &options, &mut keys, &mut Vec::new()),
+                Ok(())
+            );
+        }
+        assert_eq!(keys.keys.len(), 1);
+    }
+
+    #[test]
+    fn unknown_kid_is_error() {
+        let cose = signed_cose(vec![(Value::from(4), Value::from(vec![1u8, 2]))]);
+        let result = verify_signature(&cose, &options(TrustStore::new()), &mut Vec::new());
+        assert_eq!(result.unwrap_err().code, ErrorCode::UnknownKid)
+    }
+
+    #[test]
+    fn x5chain_issued_by_csca_is_trusted() {
+        let mut store = TrustStore::new();
+        store.add_csca(CSCA);
+
+        assert_eq!(
+            verify_signature(&x5chain_cose(), &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn x5chain_known_dsc_is_trusted() {
+        let mut store = TrustStore::new();
+        store.add_certificate(&certificate_kid(&base64::decode(DSC).unwrap()), DSC);
+
+        assert_eq!(
+            verify_signature(&x5chain_cose(), &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn x5chain_with_unknown_csca_is_untrusted() {
+        let mut store = TrustStore::new();
+        store.add_csca(OTHER_CSCA);
+
+        let result = verify_signature(&x5chain_cose(), &options(store), &mut Vec::new());
+        assert_eq!(result.unwrap_err().code, ErrorCode::UntrustedCertificate)
+    }
+
+    #[test]
+    fn x5chain_including_csca_is_trusted() {
+        let cose = signed_cose(vec![(
+            Value::from(33),
+            Value::Array(vec![
+                Value::from(base64::decode(DSC).unwrap()),
+                Value::from(base64::decode(CSCA).unwrap()),
+            ]),
+        )]);
+        let mut store = TrustStore::new();
+        store.add_csca(CSCA);
+
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn x5chain_expired_is_untrusted() {
+        let mut store = TrustStore::new();
+        store.add_csca(CSCA);
+        let mut options = options(store);
+        // 2040-01-01, after the DSC expired
+        options.set_now(2208988800.0);
+
+        let result = verify_signature(&x5chain_cose(), &options, &mut Vec::new());
+        assert_eq!(result.unwrap_err().code, ErrorCode::UntrustedCertificate)
+    }
+
+    #[test]
+    fn x5chain_not_yet_valid_is_untrusted() {
+        let mut store = TrustStore::new();
+        store.add_certificate(&certificate_kid(&base64::decode(DSC).unwrap()), DSC);
+        let mut options = options(store);
+        // 2021-01-01, before the DSC was issued
+        options.set_now(1609459200.0);
+
+        let result = verify_signature(&x5chain_cose(), &options, &mut Vec::new());
+        assert_eq!(result.unwrap_err().code, ErrorCode::UntrustedCertificate)
+    }
+
+    #[test]
+    fn x5chain_issued_by_no_certificate_authority_is_untrusted() {
+        let cose = signed_cose_with(
+            -7,
+            sign_leaf,
+            vec![(
+                Value::from(33),
+                Value::Array(vec![
+                    Value::from(base64::decode(LEAF).unwrap()),
+                    Value::from(base64::decode(DSC).unwrap()),
+                ]),
+            )],
+            &[],
+        );
+        let mut store = TrustStore::new();
+        store.add_csca(CSCA);
+
+        let error = verify_signature(&cose, &options(store), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::UntrustedCertificate);
+        assert!(error.message.contains("not a certificate authority"));
+    }
+
+    #[test]
+    fn x5chain_signed_by_csca_is_untrusted() {
+        let cose = signed_cose_with(
+            -7,
+            sign_csca,
+            vec![(
+                Value::from(33),
+                Value::from(base64::decode(SIGNING_CSCA).unwrap()),
+            )],
+            &[],
+        );
+        let mut store = TrustStore::new();
+        store.add_csca(SIGNING_CSCA);
+
+        let error = verify_signature(&cose, &options(store), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::UntrustedCertificate);
+        assert!(error.message.contains("not a document signer"));
+    }
+
+    #[test]
+    fn x5chain_with_invalid_signature_is_error() {
+        let mut cose = x5chain_cose();
+        let mut values = cose.to_be_signed(&[]);
+        values[3] = Value::from(cbor_encode(&Value::from("other_payload")));
+        cose = CoseSingleSigned::try_from([
+            values[1].clone(),
+            Value::Map(vec![(
+                Value::from(33),
+                Value::from(base64::decode(DSC).unwrap()),
+            )]),
+            values[3].clone(),
+            Value::from(cose.signature().to_vec()),
+        ])
+        .unwrap();
+        let mut store = TrustStore::new();
+        store.add_csca(CSCA);
+
+        assert!(verify_signature(&cose, &options(store), &mut Vec::new()).is_err())
+    }
+
+    #[test]
+    fn it_verifies_with_external_aad() {
+        let cose = signed_cose_with_aad(vec![(Value::from(4), Value::from(vec![1u8, 2]))], b"aad");
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+        let mut options = options(store);
+
+        assert!(verify_signature(&cose, &options, &mut Vec::new()).is_err());
+
+        options.set_external_aad(b"aad");
+        assert_eq!(verify_signature(&cose, &options, &mut Vec::new()), Ok(()))
+    }
+
+    #[test]
+    fn it_verifies_a_detached_payload() {
+        let signed = signed_cose(vec![(Value::from(4), Value::from(vec![1u8, 2]))]);
+        let to_be_signed = signed.to_be_signed(&[]);
+        let mut cose = CoseSingleSigned::try_from([
+            to_be_signed[1].clone(),
+            Value::Map(vec![(Value::from(4), Value::from(vec![1u8, 2]))]),
+            Value::Null,
+            Value::from(signed.signature().to_vec()),
+        ])
+        .unwrap();
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+
+        cose.attach_payload(to_be_signed[3].as_bytes().unwrap())
+            .unwrap();
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn it_verifies_detached_content_which_is_not_cbor() {
+        let protected = cbor_encode(&Value::Map(vec![(Value::from(1), Value::from(-7))]));
+        let unprotected = Value::Map(vec![(Value::from(4), Value::from(vec![1u8, 2]))]);
+        let mut cose = CoseSingleSigned::try_from([
+            Value::from(protected),
+            unprotected,
+            Value::Null,
+            Value::Bytes(vec![]),
+        ])
+        .unwrap();
+        cose.attach_payload(b"not cbor \xff").unwrap();
+        let to_sign = cbor_encode(&Value::Array(cose.to_be_signed(&[]).to_vec()));
+        cose.set_signature(sign_p256(&to_sign));
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+
+        assert_eq!(cose.payload(), None);
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    /// Public key of DSC
+    fn public_key() -> PublicKey {
+        let key = p256::ecdsa::SigningKey::from_slice(&DSC_KEY).unwrap();
+        PublicKey::P256(*key.verifying_key())
+    }
+
+    #[test]
+    fn der_signature_is_rejected_in_strict_mode() {
+        let signature = p256::ecdsa::Signature::from_slice(&sign_p256(b"data")).unwrap();
+        let der = signature.to_der();
+
+        let result = decode_signature(der.as_bytes(), &public_key(), false, &mut Vec::new());
+        assert!(result.unwrap_err().contains("DER encoded"))
+    }
+
+    #[test]
+    fn der_signature_is_decoded_in_lenient_mode() {
+        let signature = p256::ecdsa::Signature::from_slice(&sign_p256(b"data")).unwrap();
+        let der = signature.to_der();
+        let mut warnings = Vec::new();
+
+        assert_eq!(
+            decode_signature(der.as_bytes(), &public_key(), true, &mut warnings),
+            Ok(signature.to_vec())
+        );
+        assert_eq!(warnings.len(), 1)
+    }
+
+    #[test]
+    fn invalid_signature_length_is_error() {
+        let result = decode_signature(&[1, 2, 3], &public_key(), true, &mut Vec::new());
+        assert!(result.unwrap_err().contains("3 bytes"))
+    }
+
+    #[test]
+    fn it_verifies_es384() {
+        let kid = vec![(Value::from(4), Value::from(vec![1u8, 2]))];
+        let cose = signed_cose_with(-35, sign_p384, kid, &[]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC_P384);
+
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn it_verifies_es512() {
+        let kid = vec![(Value::from(4), Value::from(vec![1u8, 2]))];
+        let cose = signed_cose_with(-36, sign_p521, kid, &[]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC_P521);
+
+        assert_eq!(
+            verify_signature(&cose, &options(store), &mut Vec::new()),
+            Ok(())
+        )
+    }
+
+    #[test]
+    fn algorithm_not_matching_curve_is_error() {
+        let kid = vec![(Value::from(4), Value::from(vec![1u8, 2]))];
+        let cose = signed_cose_with(-7, sign_p384, kid, &[]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC_P384);
+
+        let error = verify_signature(&cose, &options(store), &mut Vec::new()).unwrap_err();
+        assert!(error.message.contains("doesn't match"));
+        assert_eq!(error.code, ErrorCode::UnsupportedAlgorithm)
+    }
+
+    #[test]
+    fn unsupported_algorithm_is_error() {
+        let kid = vec![(Value::from(4), Value::from(vec![1u8, 2]))];
+        let cose = signed_cose_with(-8, sign_p256, kid, &[]);
+        let mut store = TrustStore::new();
+        store.add_certificate("AQI=", DSC);
+
+        let error = verify_signature(&cose, &options(store), &mut Vec::new()).unwrap_err();
+        assert!(error.message.contains("Unsupported algorithm"))
+    }
+}
@@ -0,0 +1,88 @@
+//! Conformance with the DCC quality assurance test data
+//!
+//! Runs the test cases in tests/testdata, or in the directory of the
+//! DCC_TESTDATA environment variable, e.g. a checkout of
+//! https://github.com/eu-digital-green-certificates/dgc-testdata
+//!
+//! tests/testdata contains a few cases of the dgc-testdata and cases
+//! generated with a throwaway DSC for the country XY.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use dcc_wasm::TestCase;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The JSON files in the directory and its subdirectories
+fn test_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_e) => return,
+    };
+
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            test_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn it_conforms_to_the_test_data() {
+    let dir = std::env::var("DCC_TESTDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/testdata"));
+
+    let mut files = Vec::new();
+    test_files(&dir, &mut files);
+    files.sort();
+    assert!(!files.is_empty(), "No test cases in {}", dir.display());
+
+    let mut failures = Vec::new();
+    let mut skipped = Vec::new();
+    let mut passed = 0;
+    for file in &files {
+        let test_case = match fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| TestCase::from_json(&json))
+        {
+            Ok(test_case) => test_case,
+            Err(e) => {
+                failures.push(format!("{}: {}", file.display(), e));
+                continue;
+            }
+        };
+
+        for result in test_case.run() {
+            if result.failed() {
+                failures.push(format!("{}: {}", file.display(), result));
+            } else if result.skipped() {
+                skipped.push(format!("{}: {}", file.display(), result));
+            } else {
+                passed += 1;
+            }
+        }
+    }
+
+    // Unsupported stages and stages without input are neither passed nor failed
+    println!(
+        "{} stages passed, {} skipped:\n{}",
+        passed,
+        skipped.len(),
+        skipped.join("\n")
+    );
+    assert!(passed > 0, "No stages of the test cases are checked");
+
+    assert!(
+        failures.is_empty(),
+        "{} stages of {} test cases fail:\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+}
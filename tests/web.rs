@@ -4,7 +4,7 @@
 
 extern crate wasm_bindgen_test;
 
-use dcc_wasm::parse;
+use dcc_wasm::{parse, parse_with_options, ParseOptions, Validity};
 use serde_json::Value;
 use wasm_bindgen_test::*;
 
@@ -25,6 +25,28 @@ fn it_contains_props() {
     assert_eq!(json["1"], String::from("DE"))
 }
 
+#[wasm_bindgen_test]
+fn it_contains_claims() {
+    let claims = parse(TEST_DCC).claims;
+    assert_eq!(claims.issuer, Some(String::from("DE")));
+    assert_eq!(claims.expiration, Some(1643356073));
+}
+
+#[wasm_bindgen_test]
+fn it_is_expired() {
+    assert_eq!(parse(TEST_DCC).validity, Validity::Expired)
+}
+
+#[wasm_bindgen_test]
+fn it_is_valid_in_2021() {
+    let mut options = ParseOptions::new();
+    options.set_now(1630000000.0);
+    assert_eq!(
+        parse_with_options(TEST_DCC, &options).validity,
+        Validity::Valid
+    )
+}
+
 #[wasm_bindgen_test]
 fn it_is_valid() {
     assert_eq!(parse(TEST_DCC).successful, true)